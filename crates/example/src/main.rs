@@ -31,48 +31,48 @@ pub fn main() -> Result<(), Error> {
         .system(openxr::FormFactor::HEAD_MOUNTED_DISPLAY)
         .unwrap();
 
-    // Check what blend mode is valid for this device (opaque vs transparent displays). We'll just
-    // take the first one available!
-    let environment_blend_mode = xr_instance
-        .enumerate_environment_blend_modes(xr_system, VIEW_TYPE)
-        .unwrap()[0];
+    // Check what blend mode is valid for this device (opaque vs transparent displays). We prefer
+    // opaque, but transparent displays only support additive.
+    let environment_blend_mode = indite::select_environment_blend_mode(
+        &xr_instance,
+        xr_system,
+        VIEW_TYPE,
+        &[
+            openxr::EnvironmentBlendMode::OPAQUE,
+            openxr::EnvironmentBlendMode::ADDITIVE,
+        ],
+    )
+    .unwrap();
 
     let render_context = RenderContext::new(&xr_instance, xr_system);
-    let mut session_bundle = session::create_session(&xr_instance, xr_system, &render_context);
-    let action_set_bundle = actions::create_action_set(&xr_instance, &session_bundle.session);
+    let mut session_bundle = session::create_session(
+        &xr_instance,
+        xr_system,
+        environment_blend_mode,
+        &render_context,
+    );
+    let action_set_bundle =
+        actions::create_action_set(&xr_instance, session_bundle.driver.session());
 
     // Main loop
-    let mut event_storage = openxr::EventDataBuffer::new();
-    let mut session_running = false;
-
     loop {
-        let should_continue = handle_ctrlc(&ctrlc_request_exit, &session_bundle.session);
-        if !should_continue {
-            break;
-        }
-
-        let should_continue = handle_instance_events(
-            &xr_instance,
-            &session_bundle.session,
-            &mut event_storage,
-            &mut session_running,
+        handle_ctrlc(&ctrlc_request_exit, &mut session_bundle.driver);
+        handle_session_events(
+            &mut session_bundle.driver,
+            &mut session_bundle.tracking_space,
         );
-        if !should_continue {
+
+        if session_bundle.driver.should_exit() {
             break;
         }
 
-        if !session_running {
+        if !session_bundle.driver.is_running() {
             // Don't hotloop the CPU
             std::thread::sleep(Duration::from_millis(100));
             continue;
         }
 
-        rendering::render_frame(
-            environment_blend_mode,
-            &render_context,
-            &mut session_bundle,
-            &action_set_bundle,
-        );
+        rendering::render_frame(&render_context, &mut session_bundle, &action_set_bundle);
     }
 
     println!("exiting cleanly");
@@ -90,13 +90,18 @@ fn create_openxr_instance(xr_entry: &openxr::Entry) -> Result<openxr::Instance,
     // If a required extension isn't present, you want to ditch out here! It's possible something
     // like your rendering API might not be provided by the active runtime. APIs like OpenGL don't
     // have universal support.
-    if !available_extensions.khr_vulkan_enable2 {
+    if !available_extensions.khr_vulkan_enable2 && !available_extensions.khr_vulkan_enable {
         bail!("vulkan openxr extension not available");
     }
 
-    // Initialize OpenXR with the extensions we've found!
+    // Initialize OpenXR with the extensions we've found! indite prefers vulkan_enable2, and falls
+    // back to the original vulkan_enable on older runtimes.
     let mut enabled_extensions = openxr::ExtensionSet::default();
-    enabled_extensions.khr_vulkan_enable2 = true;
+    if available_extensions.khr_vulkan_enable2 {
+        enabled_extensions.khr_vulkan_enable2 = true;
+    } else {
+        enabled_extensions.khr_vulkan_enable = true;
+    }
 
     // TODO: Only enable debugging when given debugging flags
     enabled_extensions.ext_debug_utils = true;
@@ -134,10 +139,7 @@ fn create_ctrlc_handler() -> Arc<AtomicBool> {
     ctrlc_request_exit
 }
 
-fn handle_ctrlc(
-    ctrlc_request_exit: &Arc<AtomicBool>,
-    xr_session: &openxr::Session<openxr::Vulkan>,
-) -> bool {
+fn handle_ctrlc(ctrlc_request_exit: &Arc<AtomicBool>, driver: &mut indite::SessionDriver) {
     // Check for ctrl-c
     if ctrlc_request_exit.load(Ordering::Relaxed) {
         println!("ctrl-c requesting exit");
@@ -145,52 +147,29 @@ fn handle_ctrlc(
         // The OpenXR runtime may want to perform a smooth transition between scenes, so we
         // can't necessarily exit instantly. Instead, we must notify the runtime of our
         // intent and wait for it to tell us when we're actually done.
-        match xr_session.request_exit() {
-            Ok(()) => {}
-            Err(openxr::sys::Result::ERROR_SESSION_NOT_RUNNING) => return false,
-            Err(e) => panic!("{}", e),
-        }
+        driver.request_exit().unwrap();
     }
-
-    true
 }
 
-fn handle_instance_events(
-    xr_instance: &openxr::Instance,
-    xr_session: &openxr::Session<openxr::Vulkan>,
-    event_storage: &mut openxr::EventDataBuffer,
-    session_running: &mut bool,
-) -> bool {
-    while let Some(event) = xr_instance.poll_event(event_storage).unwrap() {
+fn handle_session_events(
+    driver: &mut indite::SessionDriver,
+    tracking_space: &mut indite::TrackingSpace,
+) {
+    // The driver takes care of beginning and ending the session, we just need to keep it polling
+    for event in driver.poll_events() {
+        let event = event.unwrap();
+        if tracking_space.handle_event(&event) {
+            println!("tracking space is changing");
+        }
+
         match event {
-            openxr::Event::SessionStateChanged(e) => {
-                // Session state change is where we can begin and end sessions, as well as
-                // find quit messages!
-                println!("entered state {:?}", e.state());
-                match e.state() {
-                    openxr::SessionState::READY => {
-                        xr_session.begin(VIEW_TYPE).unwrap();
-                        *session_running = true;
-                    }
-                    openxr::SessionState::STOPPING => {
-                        xr_session.end().unwrap();
-                        *session_running = false;
-                    }
-                    openxr::SessionState::EXITING | openxr::SessionState::LOSS_PENDING => {
-                        return false;
-                    }
-                    _ => {}
-                }
-            }
-            openxr::Event::InstanceLossPending(_) => {
-                return false;
+            indite::SessionEvent::StateChanged { state } => {
+                println!("entered state {:?}", state);
             }
-            openxr::Event::EventsLost(e) => {
-                println!("lost {} events", e.lost_event_count());
+            indite::SessionEvent::EventsLost { count } => {
+                println!("lost {} events", count);
             }
             _ => {}
         }
     }
-
-    true
 }
@@ -23,17 +23,16 @@ pub struct RenderContext {
     pub queue: Queue,
 
     pub uniform_layout: BindGroupLayout,
-    pub render_pipeline: RenderPipeline,
 }
 
 impl RenderContext {
     pub fn new(xr_instance: &openxr::Instance, xr_system: openxr::SystemId) -> Self {
         let instance = indite::create_instance(xr_instance, xr_system).unwrap();
-        let (device, queue) = indite::create_device(xr_instance, xr_system, &instance).unwrap();
+        let device_config = indite::DeviceConfig::default();
+        let (device, queue, _) =
+            indite::create_device(xr_instance, xr_system, &instance, &device_config).unwrap();
 
-        // Create WPGU render pipeline
         let uniform_layout = create_uniform_layout(&device);
-        let render_pipeline = create_render_pipeline(&device, &uniform_layout);
 
         Self {
             instance,
@@ -41,7 +40,6 @@ impl RenderContext {
             queue,
 
             uniform_layout,
-            render_pipeline,
         }
     }
 }
@@ -89,9 +87,10 @@ fn create_uniform_bind_group(device: &Device, layout: &BindGroupLayout) -> (Buff
     (buffer, bind_group)
 }
 
-fn create_render_pipeline(
+pub fn create_render_pipeline(
     wgpu_device: &Device,
     uniform_layout: &BindGroupLayout,
+    format: TextureFormat,
 ) -> RenderPipeline {
     let shader = wgpu_device.create_shader_module(ShaderModuleDescriptor {
         label: None,
@@ -117,7 +116,7 @@ fn create_render_pipeline(
             module: &shader,
             entry_point: Some("fs_main"),
             compilation_options: Default::default(),
-            targets: &[Some(TextureFormat::Rgba8UnormSrgb.into())],
+            targets: &[Some(format.into())],
         }),
         primitive: PrimitiveState::default(),
         depth_stencil: None,
@@ -130,61 +129,50 @@ fn create_render_pipeline(
     })
 }
 
-#[allow(clippy::too_many_arguments)]
 pub fn render_frame(
-    environment_blend_mode: openxr::EnvironmentBlendMode,
     render_context: &RenderContext,
     session_bundle: &mut SessionBundle,
     action_set_bundle: &ActionSetBundle,
 ) {
     // Block until the previous frame is finished displaying, and is ready for another one.
-    // Also returns a prediction of when the next frame will be displayed, for use with
-    // predicting locations of controllers, viewpoints, etc.
-    let xr_frame_state = session_bundle.frame_wait.wait().unwrap();
-
-    // Must be called before any rendering is done!
-    session_bundle.frame_stream.begin().unwrap();
-
-    if !xr_frame_state.should_render {
-        session_bundle
-            .frame_stream
-            .end(
-                xr_frame_state.predicted_display_time,
-                environment_blend_mode,
-                &[],
-            )
-            .unwrap();
+    // The frame contains a prediction of when it will be displayed, for use with predicting
+    // locations of controllers, viewpoints, etc.
+    // If the runtime doesn't want us to render this frame, it has already been ended for us.
+    let Some(frame) = session_bundle.frame_loop.begin_frame().unwrap() else {
         return;
-    }
+    };
+
+    // Emulated spaces may need to be derived again, before we locate anything in them
+    session_bundle
+        .tracking_space
+        .update(frame.predicted_display_time())
+        .unwrap();
+    let xr_space = session_bundle.tracking_space.space();
 
     // We need to ask which swapchain image to use for rendering! Which one will we get?
     // Who knows! It's up to the runtime to decide.
-    let image_index = session_bundle
-        .swapchain_handle
-        .lock()
-        .unwrap()
-        .acquire_image()
-        .unwrap();
+    let image = session_bundle.swapchain.acquire().unwrap();
 
-    // Get the view for this frame
-    let (_, view) = &session_bundle.swapchain_textures[image_index as usize];
+    // Wait until the image is available to render to. The compositor could still be reading from
+    // it, so we don't get access to the image's view until we've waited.
+    let image = image.wait_infinite().unwrap();
 
     // Record the command buffer
     let (uniform_buffer, uniform_bind_group) =
         create_uniform_bind_group(&render_context.device, &render_context.uniform_layout);
     let command_buffer = record_command_buffer(
         &render_context.device,
-        &render_context.render_pipeline,
+        &session_bundle.render_pipeline,
         &session_bundle.multisampled_framebuffer,
-        view,
+        image.view(),
         &uniform_bind_group,
     );
 
     actions::read_actions(
-        &session_bundle.session,
+        session_bundle.driver.session(),
         action_set_bundle,
-        &session_bundle.stage,
-        &xr_frame_state,
+        xr_space,
+        frame.predicted_display_time(),
     );
 
     // Fetch the view transforms. To minimize latency, we intentionally do this *after*
@@ -193,35 +181,19 @@ pub fn render_frame(
     // to the GPU just-in-time by writing them to per-frame host-visible memory which the
     // GPU will only read once the command buffer is submitted.
     let (_, xr_views) = session_bundle
-        .session
-        .locate_views(
-            VIEW_TYPE,
-            xr_frame_state.predicted_display_time,
-            &session_bundle.stage,
-        )
+        .driver
+        .session()
+        .locate_views(VIEW_TYPE, frame.predicted_display_time(), xr_space)
         .unwrap();
 
     // Update bind group buffer with the eyes' matrices, as late as possible
     write_uniform_buffer(&uniform_buffer, &xr_views);
 
-    // Wait until the image is available to render to before beginning work on the GPU. The
-    // compositor could still be reading from it.
-    let mut xr_swapchain = session_bundle.swapchain_handle.lock().unwrap();
-    xr_swapchain.wait_image(openxr::Duration::INFINITE).unwrap();
-
     // Submit the previously prepared command buffer
     render_context.queue.submit(Some(command_buffer));
 
-    xr_swapchain.release_image().unwrap();
-    end_frame(
-        environment_blend_mode,
-        &mut session_bundle.frame_stream,
-        &session_bundle.swapchain_desc,
-        &xr_swapchain,
-        &session_bundle.stage,
-        &xr_views,
-        &xr_frame_state,
-    );
+    image.release().unwrap();
+    end_frame(frame, &session_bundle.swapchain, xr_space, &xr_views);
 }
 
 fn record_command_buffer(
@@ -273,50 +245,15 @@ fn write_uniform_buffer(buffer: &Buffer, xr_views: &[openxr::View]) {
 }
 
 fn end_frame(
-    environment_blend_mode: openxr::EnvironmentBlendMode,
-    frame_stream: &mut openxr::FrameStream<openxr::Vulkan>,
-    swapchain_desc: &indite::SwapchainDescriptor,
-    xr_swapchain: &openxr::Swapchain<openxr::Vulkan>,
-    xr_stage: &openxr::Space,
+    frame: indite::Frame<'_>,
+    swapchain: &indite::Swapchain,
+    xr_space: &openxr::Space,
     xr_views: &[openxr::View],
-    xr_frame_state: &openxr::FrameState,
 ) {
     // Tell OpenXR what to present for this frame
-    let rect = openxr::Rect2Di {
-        offset: openxr::Offset2Di { x: 0, y: 0 },
-        extent: openxr::Extent2Di {
-            width: swapchain_desc.width as _,
-            height: swapchain_desc.height as _,
-        },
-    };
-    let views = [
-        openxr::CompositionLayerProjectionView::new()
-            .pose(xr_views[0].pose)
-            .fov(xr_views[0].fov)
-            .sub_image(
-                openxr::SwapchainSubImage::new()
-                    .swapchain(xr_swapchain)
-                    .image_array_index(0)
-                    .image_rect(rect),
-            ),
-        openxr::CompositionLayerProjectionView::new()
-            .pose(xr_views[1].pose)
-            .fov(xr_views[1].fov)
-            .sub_image(
-                openxr::SwapchainSubImage::new()
-                    .swapchain(xr_swapchain)
-                    .image_array_index(1)
-                    .image_rect(rect),
-            ),
-    ];
-    let layer = openxr::CompositionLayerProjection::new()
-        .space(xr_stage)
-        .views(&views);
-    frame_stream
-        .end(
-            xr_frame_state.predicted_display_time,
-            environment_blend_mode,
-            &[&layer],
-        )
+    let layer = indite::ProjectionLayerBuilder::new(swapchain, xr_views, xr_space)
+        .environment_blend_mode(frame.environment_blend_mode())
+        .build()
         .unwrap();
+    frame.end_frame(&[&layer.layer()]).unwrap();
 }
@@ -1,29 +1,33 @@
-use indite::{SwapchainDescriptor, SwapchainHandle};
-use wgpu::{Texture, TextureFormat, TextureUsages, TextureView};
+use indite::{
+    FrameLoop, ReferenceSpaceKind, SessionDriver, Swapchain, SwapchainDescriptor, TrackingSpace,
+};
+use wgpu::{RenderPipeline, TextureFormat, TextureUsages, TextureView};
 
-use crate::{rendering::RenderContext, VIEW_COUNT, VIEW_TYPE};
+use crate::{
+    rendering::{self, RenderContext},
+    VIEW_COUNT, VIEW_TYPE,
+};
 
 pub struct SessionBundle {
-    pub session: openxr::Session<openxr::Vulkan>,
-    pub frame_wait: openxr::FrameWaiter,
-    pub frame_stream: openxr::FrameStream<openxr::Vulkan>,
+    pub driver: SessionDriver,
+    pub frame_loop: FrameLoop,
 
-    pub swapchain_desc: SwapchainDescriptor,
-    pub swapchain_handle: SwapchainHandle,
-    pub swapchain_textures: Vec<(Texture, TextureView)>,
-    pub stage: openxr::Space,
+    pub swapchain: Swapchain,
+    pub tracking_space: TrackingSpace,
 
     pub multisampled_framebuffer: TextureView,
+    pub render_pipeline: RenderPipeline,
 }
 
 pub fn create_session(
     xr_instance: &openxr::Instance,
     xr_system: openxr::SystemId,
+    environment_blend_mode: openxr::EnvironmentBlendMode,
     render_context: &RenderContext,
 ) -> SessionBundle {
     // A session represents this application's desire to display things! This is where we hook
-    // up our graphics API. This does not start the session; for that, you'll need a call to
-    // Session::begin, which we do in 'main_loop below.
+    // up our graphics API. This does not start the session; the session driver begins it when the
+    // runtime tells us it's ready, while polling events in the main loop.
     let (xr_session, frame_wait, frame_stream) = indite::create_session(
         xr_instance,
         xr_system,
@@ -47,37 +51,57 @@ pub fn create_session(
         width: xr_view_configs[0].recommended_image_rect_width,
         height: xr_view_configs[0].recommended_image_rect_height,
         view_count: VIEW_COUNT,
+        formats: vec![TextureFormat::Rgba8UnormSrgb, TextureFormat::Bgra8UnormSrgb],
+        foveation: None,
     };
-    let (swapchain_handle, swapchain_textures) =
+    let swapchain =
         indite::create_swapchain(&render_context.device, &xr_session, &swapchain_desc).unwrap();
 
     // OpenXR uses a couple different types of reference frames for positioning content; we need
     // to choose one for displaying our content! STAGE would be relative to the center of your
     // guardian system's bounds, and LOCAL would be relative to your device's starting location.
-    let stage = xr_session
-        .create_reference_space(openxr::ReferenceSpaceType::STAGE, openxr::Posef::IDENTITY)
-        .unwrap();
+    // Not every setup has a calibrated stage, so fall back to the floor below the starting
+    // location, and finally to the starting location itself.
+    let tracking_space = TrackingSpace::new(
+        &xr_session,
+        &[
+            ReferenceSpaceKind::Stage,
+            ReferenceSpaceKind::LocalFloor,
+            ReferenceSpaceKind::Local,
+        ],
+    )
+    .unwrap();
+    println!("tracking in {:?} space", tracking_space.kind());
 
-    let multisampled_framebuffer =
-        create_multisampled_framebuffer(&render_context.device, &swapchain_desc);
+    let multisampled_framebuffer = create_multisampled_framebuffer(
+        &render_context.device,
+        swapchain.desc(),
+        swapchain.format(),
+    );
+
+    // The pipeline has to match the format the runtime gave us
+    let render_pipeline = rendering::create_render_pipeline(
+        &render_context.device,
+        &render_context.uniform_layout,
+        swapchain.format(),
+    );
 
     SessionBundle {
-        session: xr_session,
-        frame_wait,
-        frame_stream,
+        driver: SessionDriver::new(xr_session, VIEW_TYPE),
+        frame_loop: FrameLoop::new(frame_wait, frame_stream, environment_blend_mode),
 
-        swapchain_desc,
-        swapchain_handle,
-        swapchain_textures,
-        stage,
+        swapchain,
+        tracking_space,
 
         multisampled_framebuffer,
+        render_pipeline,
     }
 }
 
 fn create_multisampled_framebuffer(
     device: &wgpu::Device,
     swapchain_desc: &SwapchainDescriptor,
+    format: TextureFormat,
 ) -> wgpu::TextureView {
     let size = wgpu::Extent3d {
         width: swapchain_desc.width,
@@ -90,7 +114,7 @@ fn create_multisampled_framebuffer(
         mip_level_count: 1,
         sample_count: 4,
         dimension: wgpu::TextureDimension::D2,
-        format: TextureFormat::Rgba8UnormSrgb,
+        format,
         usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::TRANSIENT,
         view_formats: &[],
     };
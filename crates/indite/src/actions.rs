@@ -0,0 +1,543 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::Deserialize;
+
+use crate::{
+    Error,
+    input::{ActionValue, InputSnapshot, PoseState, TrackedPose},
+};
+
+/// Declarative description of an application's action sets, actions, and suggested bindings.
+///
+/// Can be loaded from TOML or JSON. Actions are referred to as `set/action` in bindings and when
+/// looking them up on `Actions`.
+///
+/// ```toml
+/// [[action_sets]]
+/// name = "gameplay"
+/// localized_name = "Gameplay"
+///
+/// [[action_sets.actions]]
+/// name = "grab"
+/// localized_name = "Grab"
+/// type = "float"
+/// subaction_paths = ["/user/hand/left", "/user/hand/right"]
+///
+/// [[interaction_profiles]]
+/// profile = "/interaction_profiles/khr/simple_controller"
+///
+/// [interaction_profiles.bindings]
+/// "gameplay/grab" = ["/user/hand/left/input/select/click", "/user/hand/right/input/select/click"]
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ActionManifest {
+    #[serde(default)]
+    pub action_sets: Vec<ActionSetManifest>,
+    /// Suggested bindings, the runtime picks which profile to use based on the connected devices.
+    #[serde(default)]
+    pub interaction_profiles: Vec<InteractionProfileManifest>,
+}
+
+impl ActionManifest {
+    pub fn from_toml(source: &str) -> Result<Self, Error> {
+        toml::from_str(source).map_err(Error::ManifestToml)
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, Error> {
+        serde_json::from_str(source).map_err(Error::ManifestJson)
+    }
+
+    /// Loads a manifest from a `.toml` or `.json` file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let source = std::fs::read_to_string(path).map_err(|source| Error::ManifestIo {
+            path: path.to_owned(),
+            source,
+        })?;
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml(&source),
+            Some("json") => Self::from_json(&source),
+            _ => Err(Error::UnknownManifestFormat(path.to_owned())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActionSetManifest {
+    pub name: String,
+    pub localized_name: String,
+    /// Higher priority sets take precedence when multiple sets bind the same input.
+    #[serde(default)]
+    pub priority: u32,
+    #[serde(default)]
+    pub actions: Vec<ActionManifestEntry>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ActionManifestEntry {
+    pub name: String,
+    pub localized_name: String,
+    #[serde(rename = "type")]
+    pub ty: ActionType,
+    /// Top level user paths, like `/user/hand/left`, to be able to query the action per path.
+    #[serde(default)]
+    pub subaction_paths: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionType {
+    Bool,
+    Float,
+    Vector2f,
+    Pose,
+    Haptic,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InteractionProfileManifest {
+    /// Path of the interaction profile, like `/interaction_profiles/khr/simple_controller`.
+    pub profile: String,
+    /// Input and output paths to bind, by `set/action`.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Vec<String>>,
+}
+
+/// Action of any type, created from an `ActionManifest`.
+pub enum AnyAction {
+    Bool(openxr::Action<bool>),
+    Float(openxr::Action<f32>),
+    Vector2f(openxr::Action<openxr::Vector2f>),
+    Pose(openxr::Action<openxr::Posef>),
+    Haptic(openxr::Action<openxr::Haptic>),
+}
+
+impl AnyAction {
+    pub fn ty(&self) -> ActionType {
+        match self {
+            AnyAction::Bool(_) => ActionType::Bool,
+            AnyAction::Float(_) => ActionType::Float,
+            AnyAction::Vector2f(_) => ActionType::Vector2f,
+            AnyAction::Pose(_) => ActionType::Pose,
+            AnyAction::Haptic(_) => ActionType::Haptic,
+        }
+    }
+
+    fn binding(&self, path: openxr::Path) -> openxr::Binding<'_> {
+        match self {
+            AnyAction::Bool(action) => openxr::Binding::new(action, path),
+            AnyAction::Float(action) => openxr::Binding::new(action, path),
+            AnyAction::Vector2f(action) => openxr::Binding::new(action, path),
+            AnyAction::Pose(action) => openxr::Binding::new(action, path),
+            AnyAction::Haptic(action) => openxr::Binding::new(action, path),
+        }
+    }
+}
+
+/// Action sets and actions created from an `ActionManifest`, attached to a session.
+///
+/// Pose actions get a space for every subaction path, and one for `Path::NULL`, to locate them.
+pub struct Actions {
+    action_sets: Vec<(String, openxr::ActionSet)>,
+    actions: HashMap<String, ActionEntry>,
+}
+
+struct ActionEntry {
+    action: AnyAction,
+    /// `Path::NULL`, followed by the subaction paths of the action.
+    paths: Vec<openxr::Path>,
+    /// Spaces of pose actions, by index in `paths`.
+    spaces: Vec<openxr::Space>,
+}
+
+impl Actions {
+    pub fn action_set(&self, name: &str) -> Option<&openxr::ActionSet> {
+        self.action_sets
+            .iter()
+            .find(|(set_name, _)| set_name == name)
+            .map(|(_, action_set)| action_set)
+    }
+
+    /// Gets an action by `set/action` name.
+    pub fn get(&self, name: &str) -> Option<&AnyAction> {
+        self.actions.get(name).map(|entry| &entry.action)
+    }
+
+    /// Gets the space of a pose action, for a subaction path or `Path::NULL`.
+    pub fn pose_space(&self, name: &str, subaction_path: openxr::Path) -> Option<&openxr::Space> {
+        let entry = self.actions.get(name)?;
+        let index = entry
+            .paths
+            .iter()
+            .position(|path| *path == subaction_path)?;
+        entry.spaces.get(index)
+    }
+
+    pub fn bool_action(&self, name: &str) -> Result<&openxr::Action<bool>, Error> {
+        match self.lookup(name)? {
+            AnyAction::Bool(action) => Ok(action),
+            action => Err(type_mismatch(name, action, ActionType::Bool)),
+        }
+    }
+
+    pub fn float_action(&self, name: &str) -> Result<&openxr::Action<f32>, Error> {
+        match self.lookup(name)? {
+            AnyAction::Float(action) => Ok(action),
+            action => Err(type_mismatch(name, action, ActionType::Float)),
+        }
+    }
+
+    pub fn vector2f_action(&self, name: &str) -> Result<&openxr::Action<openxr::Vector2f>, Error> {
+        match self.lookup(name)? {
+            AnyAction::Vector2f(action) => Ok(action),
+            action => Err(type_mismatch(name, action, ActionType::Vector2f)),
+        }
+    }
+
+    pub fn pose_action(&self, name: &str) -> Result<&openxr::Action<openxr::Posef>, Error> {
+        match self.lookup(name)? {
+            AnyAction::Pose(action) => Ok(action),
+            action => Err(type_mismatch(name, action, ActionType::Pose)),
+        }
+    }
+
+    pub fn haptic_action(&self, name: &str) -> Result<&openxr::Action<openxr::Haptic>, Error> {
+        match self.lookup(name)? {
+            AnyAction::Haptic(action) => Ok(action),
+            action => Err(type_mismatch(name, action, ActionType::Haptic)),
+        }
+    }
+
+    /// Syncs the state of every action set with the runtime.
+    ///
+    /// Call this once per frame, before reading action states.
+    pub fn sync(&self, xr_session: &openxr::Session<openxr::Vulkan>) -> Result<(), Error> {
+        let active_action_sets: Vec<_> = self
+            .action_sets
+            .iter()
+            .map(|(_, action_set)| openxr::ActiveActionSet::new(action_set))
+            .collect();
+        xr_session.sync_actions(&active_action_sets)?;
+
+        Ok(())
+    }
+
+    /// Syncs the action sets, and captures the state of every action.
+    ///
+    /// Call this once per frame. Poses are located in `base_space` at `time`, which should be the
+    /// predicted display time of the frame.
+    pub fn snapshot(
+        &self,
+        xr_session: &openxr::Session<openxr::Vulkan>,
+        base_space: &openxr::Space,
+        time: openxr::Time,
+    ) -> Result<InputSnapshot, Error> {
+        self.sync(xr_session)?;
+
+        let mut states = HashMap::with_capacity(self.actions.len());
+        for (name, entry) in &self.actions {
+            let mut action_states = Vec::with_capacity(entry.paths.len());
+            for (index, &path) in entry.paths.iter().enumerate() {
+                let state = match &entry.action {
+                    AnyAction::Bool(action) => ActionValue::Bool(action.state(xr_session, path)?),
+                    AnyAction::Float(action) => ActionValue::Float(action.state(xr_session, path)?),
+                    AnyAction::Vector2f(action) => {
+                        ActionValue::Vector2f(action.state(xr_session, path)?)
+                    }
+                    AnyAction::Pose(action) => {
+                        let location = entry.spaces[index].locate(base_space, time)?;
+                        ActionValue::Pose(PoseState {
+                            is_active: action.is_active(xr_session, path)?,
+                            location: TrackedPose::from_xr(location),
+                        })
+                    }
+                    // Haptic actions are outputs, they don't have any state
+                    AnyAction::Haptic(_) => continue,
+                };
+                action_states.push((path, state));
+            }
+            states.insert(name.clone(), action_states);
+        }
+
+        Ok(InputSnapshot::new(time, states))
+    }
+
+    fn lookup(&self, name: &str) -> Result<&AnyAction, Error> {
+        self.actions
+            .get(name)
+            .map(|entry| &entry.action)
+            .ok_or_else(|| Error::UnknownAction(name.to_owned()))
+    }
+}
+
+fn type_mismatch(name: &str, action: &AnyAction, expected: ActionType) -> Error {
+    Error::ActionTypeMismatch {
+        name: name.to_owned(),
+        actual: action.ty(),
+        expected,
+    }
+}
+
+/// Creates the action sets and actions in the manifest, suggests its bindings, and attaches the
+/// action sets to the session.
+///
+/// Action sets can only be attached to a session once, so every action set the application uses
+/// should be in the manifest.
+pub fn create_actions(
+    xr_instance: &openxr::Instance,
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    manifest: &ActionManifest,
+) -> Result<Actions, Error> {
+    let mut action_sets = Vec::new();
+    let mut actions = HashMap::new();
+
+    for set_manifest in &manifest.action_sets {
+        let action_set = xr_instance
+            .create_action_set(
+                &set_manifest.name,
+                &set_manifest.localized_name,
+                set_manifest.priority,
+            )
+            .map_err(|source| Error::ActionSetup {
+                name: set_manifest.name.clone(),
+                source,
+            })?;
+
+        for action_manifest in &set_manifest.actions {
+            let name = format!("{}/{}", set_manifest.name, action_manifest.name);
+            let entry =
+                create_action(xr_instance, &action_set, action_manifest).map_err(|source| {
+                    Error::ActionSetup {
+                        name: name.clone(),
+                        source,
+                    }
+                })?;
+            actions.insert(name, entry);
+        }
+
+        action_sets.push((set_manifest.name.clone(), action_set));
+    }
+
+    for profile_manifest in &manifest.interaction_profiles {
+        suggest_bindings(xr_instance, &actions, profile_manifest)?;
+    }
+
+    let attached: Vec<_> = action_sets
+        .iter()
+        .map(|(_, action_set)| action_set)
+        .collect();
+    xr_session.attach_action_sets(&attached)?;
+
+    for (name, entry) in &mut actions {
+        let AnyAction::Pose(action) = &entry.action else {
+            continue;
+        };
+
+        for &path in &entry.paths {
+            let space = action
+                .create_space(xr_session, path, openxr::Posef::IDENTITY)
+                .map_err(|source| Error::ActionSetup {
+                    name: name.clone(),
+                    source,
+                })?;
+            entry.spaces.push(space);
+        }
+    }
+
+    Ok(Actions {
+        action_sets,
+        actions,
+    })
+}
+
+fn create_action(
+    xr_instance: &openxr::Instance,
+    action_set: &openxr::ActionSet,
+    manifest: &ActionManifestEntry,
+) -> Result<ActionEntry, openxr::sys::Result> {
+    let subaction_paths = manifest
+        .subaction_paths
+        .iter()
+        .map(|path| xr_instance.string_to_path(path))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let name = &manifest.name;
+    let localized_name = &manifest.localized_name;
+    let action = match manifest.ty {
+        ActionType::Bool => {
+            AnyAction::Bool(action_set.create_action(name, localized_name, &subaction_paths)?)
+        }
+        ActionType::Float => {
+            AnyAction::Float(action_set.create_action(name, localized_name, &subaction_paths)?)
+        }
+        ActionType::Vector2f => {
+            AnyAction::Vector2f(action_set.create_action(name, localized_name, &subaction_paths)?)
+        }
+        ActionType::Pose => {
+            AnyAction::Pose(action_set.create_action(name, localized_name, &subaction_paths)?)
+        }
+        ActionType::Haptic => {
+            AnyAction::Haptic(action_set.create_action(name, localized_name, &subaction_paths)?)
+        }
+    };
+
+    let paths = std::iter::once(openxr::Path::NULL)
+        .chain(subaction_paths)
+        .collect();
+
+    Ok(ActionEntry {
+        action,
+        paths,
+        spaces: Vec::new(),
+    })
+}
+
+fn suggest_bindings(
+    xr_instance: &openxr::Instance,
+    actions: &HashMap<String, ActionEntry>,
+    manifest: &InteractionProfileManifest,
+) -> Result<(), Error> {
+    // Failures are reported against the profile, the runtime doesn't say which binding it rejects
+    let profile_setup = |source| Error::ActionSetup {
+        name: manifest.profile.clone(),
+        source,
+    };
+
+    let mut bindings = Vec::new();
+    for (name, paths) in &manifest.bindings {
+        let entry = actions
+            .get(name)
+            .ok_or_else(|| Error::UnknownAction(name.clone()))?;
+
+        for path in paths {
+            let path = xr_instance.string_to_path(path).map_err(profile_setup)?;
+            bindings.push(entry.action.binding(path));
+        }
+    }
+
+    let profile = xr_instance
+        .string_to_path(&manifest.profile)
+        .map_err(profile_setup)?;
+    xr_instance
+        .suggest_interaction_profile_bindings(profile, &bindings)
+        .map_err(profile_setup)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOML_MANIFEST: &str = r#"
+[[action_sets]]
+name = "gameplay"
+localized_name = "Gameplay"
+priority = 1
+
+[[action_sets.actions]]
+name = "grab"
+localized_name = "Grab"
+type = "float"
+subaction_paths = ["/user/hand/left", "/user/hand/right"]
+
+[[action_sets.actions]]
+name = "hand_pose"
+localized_name = "Hand Pose"
+type = "pose"
+
+[[interaction_profiles]]
+profile = "/interaction_profiles/khr/simple_controller"
+
+[interaction_profiles.bindings]
+"gameplay/grab" = ["/user/hand/left/input/select/click"]
+"#;
+
+    #[test]
+    fn parses_toml_manifest() {
+        let manifest = ActionManifest::from_toml(TOML_MANIFEST).unwrap();
+
+        assert_eq!(manifest.action_sets.len(), 1);
+        let set = &manifest.action_sets[0];
+        assert_eq!(set.name, "gameplay");
+        assert_eq!(set.priority, 1);
+        assert_eq!(set.actions.len(), 2);
+        assert_eq!(set.actions[0].ty, ActionType::Float);
+        assert_eq!(set.actions[0].subaction_paths.len(), 2);
+        assert_eq!(set.actions[1].ty, ActionType::Pose);
+        assert!(set.actions[1].subaction_paths.is_empty());
+
+        let profile = &manifest.interaction_profiles[0];
+        assert_eq!(
+            profile.profile,
+            "/interaction_profiles/khr/simple_controller"
+        );
+        assert_eq!(
+            profile.bindings["gameplay/grab"],
+            ["/user/hand/left/input/select/click"]
+        );
+    }
+
+    #[test]
+    fn parses_json_manifest() {
+        let source = r#"{
+            "action_sets": [{
+                "name": "menu",
+                "localized_name": "Menu",
+                "actions": [
+                    { "name": "rumble", "localized_name": "Rumble", "type": "haptic" },
+                    { "name": "scroll", "localized_name": "Scroll", "type": "vector2f" }
+                ]
+            }]
+        }"#;
+        let manifest = ActionManifest::from_json(source).unwrap();
+
+        let set = &manifest.action_sets[0];
+        assert_eq!(set.priority, 0);
+        assert_eq!(set.actions[0].ty, ActionType::Haptic);
+        assert_eq!(set.actions[1].ty, ActionType::Vector2f);
+        assert!(manifest.interaction_profiles.is_empty());
+    }
+
+    #[test]
+    fn rejects_unknown_action_type() {
+        let source = r#"{
+            "action_sets": [{
+                "name": "menu",
+                "localized_name": "Menu",
+                "actions": [{ "name": "poke", "localized_name": "Poke", "type": "int" }]
+            }]
+        }"#;
+
+        assert!(matches!(
+            ActionManifest::from_json(source),
+            Err(Error::ManifestJson(_))
+        ));
+    }
+
+    #[test]
+    fn loads_by_extension() {
+        let dir = std::env::temp_dir().join(format!("indite-manifest-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let toml_path = dir.join("actions.toml");
+        std::fs::write(&toml_path, TOML_MANIFEST).unwrap();
+        let manifest = ActionManifest::load(&toml_path).unwrap();
+        assert_eq!(manifest.action_sets[0].name, "gameplay");
+
+        let yaml_path = dir.join("actions.yaml");
+        std::fs::write(&yaml_path, "action_sets: []").unwrap();
+        let result = ActionManifest::load(&yaml_path);
+        assert!(matches!(result, Err(Error::UnknownManifestFormat(path)) if path == yaml_path));
+
+        let missing_path = dir.join("missing.toml");
+        let result = ActionManifest::load(&missing_path);
+        assert!(matches!(result, Err(Error::ManifestIo { path, .. }) if path == missing_path));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
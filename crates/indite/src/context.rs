@@ -1,6 +1,5 @@
-use std::ffi::CStr;
+use std::ffi::{CStr, CString};
 
-use anyhow::{Context, Error, bail};
 use ash::vk::{self, Handle};
 use wgpu::{
     Device, DeviceDescriptor, ExperimentalFeatures, Features, Instance, InstanceFlags, Limits,
@@ -8,130 +7,399 @@ use wgpu::{
     hal::{Api, ExposedAdapter, api::Vulkan},
 };
 
+use crate::{
+    Error, VulkanEnable,
+    vulkan_enable::{legacy_device_extensions, legacy_instance_extensions, merge_extensions},
+};
+
+/// Vulkan version indite needs at least.
+///
+/// Vulkan 1.1 guarantees multiview support. It seems WGPU internally is promoting
+/// timeline_semaphore, using the core version. This isn't actually core in 1.1 however, so use 1.2
+/// for now.
+const MIN_VULKAN_VERSION: openxr::Version = openxr::Version::new(1, 2, 0);
+
+/// Creates the WGPU instance for the OpenXR system, with the default `InstanceBuilder` settings.
 pub fn create_instance(
     xr_instance: &openxr::Instance,
     xr_system: openxr::SystemId,
 ) -> Result<Instance, Error> {
-    // Vulkan 1.1 guarantees multiview support
-    // It seems WGPU internally is promoting timeline_semaphore, using the core version. This isn't
-    //  actually core in 1.1 however, so use 1.2 for now.
-    let vk_target_version = vk::make_api_version(0, 1, 2, 0);
-    let vk_target_version_xr = openxr::Version::new(1, 2, 0);
-
-    // The `graphics_requirement` call is a required call. If you don't do it before anything else,
-    // things break! No, really. If your runtime doesn't break if you don't call this, good for you,
-    // but mine did! It was a gaint pain to debug! So don't remove this call!
-    let reqs = xr_instance.graphics_requirements::<openxr::Vulkan>(xr_system)?;
-
-    if vk_target_version_xr < reqs.min_api_version_supported
-        || vk_target_version_xr.major() > reqs.max_api_version_supported.major()
-    {
-        bail!(
-            "openxr runtime requires vulkan version > {}, <= {}",
-            reqs.min_api_version_supported,
-            reqs.max_api_version_supported.major()
-        );
+    let (instance, _) = InstanceBuilder::new(xr_instance, xr_system).build()?;
+    Ok(instance)
+}
+
+/// Builds the WGPU instance for an OpenXR system, letting the runtime create the Vulkan instance.
+pub struct InstanceBuilder<'a> {
+    xr_instance: &'a openxr::Instance,
+    xr_system: openxr::SystemId,
+    vulkan_version: openxr::Version,
+    extensions: Vec<&'static CStr>,
+    layers: Vec<&'static CStr>,
+    flags: InstanceFlags,
+    application_name: Option<&'a CStr>,
+    application_version: openxr::Version,
+    engine_name: Option<&'a CStr>,
+    engine_version: openxr::Version,
+}
+
+impl<'a> InstanceBuilder<'a> {
+    pub fn new(xr_instance: &'a openxr::Instance, xr_system: openxr::SystemId) -> Self {
+        Self {
+            xr_instance,
+            xr_system,
+            vulkan_version: MIN_VULKAN_VERSION,
+            extensions: Vec::new(),
+            layers: Vec::new(),
+            flags: InstanceFlags::empty(),
+            application_name: None,
+            application_version: openxr::Version::new(0, 0, 0),
+            engine_name: None,
+            engine_version: openxr::Version::new(0, 0, 0),
+        }
+    }
+
+    /// Vulkan version to target, defaults to 1.2, which is also the minimum.
+    ///
+    /// Must be in the range the runtime supports, see `openxr::Instance::graphics_requirements`.
+    pub fn vulkan_version(mut self, vulkan_version: openxr::Version) -> Self {
+        self.vulkan_version = vulkan_version;
+        self
+    }
+
+    /// Instance extensions to enable, on top of the ones WGPU needs.
+    pub fn extensions(mut self, extensions: &[&'static CStr]) -> Self {
+        self.extensions.extend_from_slice(extensions);
+        self
+    }
+
+    /// Instance layers to enable.
+    ///
+    /// WGPU only enables the validation layer itself when it creates the instance, so for
+    /// `InstanceFlags::VALIDATION` to have effect add `VK_LAYER_KHRONOS_validation` here.
+    pub fn layers(mut self, layers: &[&'static CStr]) -> Self {
+        self.layers.extend_from_slice(layers);
+        self
+    }
+
+    /// Debugging and validation flags passed to WGPU.
+    pub fn flags(mut self, flags: InstanceFlags) -> Self {
+        self.flags = flags;
+        self
     }
 
-    let vk_entry = unsafe { ash::Entry::load()? };
+    pub fn application(mut self, name: &'a CStr, version: openxr::Version) -> Self {
+        self.application_name = Some(name);
+        self.application_version = version;
+        self
+    }
 
-    let (vk_instance, extensions, flags) =
-        unsafe { create_vk_instance(xr_instance, xr_system, &vk_entry, vk_target_version)? };
+    pub fn engine(mut self, name: &'a CStr, version: openxr::Version) -> Self {
+        self.engine_name = Some(name);
+        self.engine_version = version;
+        self
+    }
 
-    // Create the WPGU instance from the raw instance
-    let hal_instance = unsafe {
-        <Vulkan as Api>::Instance::from_raw(
-            vk_entry,
-            vk_instance,
+    /// Creates the instance, and reports what was enabled on it.
+    ///
+    /// Fails without creating anything if the version isn't supported, or if any of the
+    /// extensions or layers aren't available.
+    pub fn build(self) -> Result<(Instance, InstanceInfo), Error> {
+        if self.vulkan_version < MIN_VULKAN_VERSION {
+            return Err(Error::VulkanVersionTooLow {
+                target: self.vulkan_version,
+                minimum: MIN_VULKAN_VERSION,
+            });
+        }
+
+        let vulkan_enable = VulkanEnable::detect(self.xr_instance)?;
+
+        // The `graphics_requirement` call is a required call. If you don't do it before anything
+        // else, things break! No, really. If your runtime doesn't break if you don't call this,
+        // good for you, but mine did! It was a gaint pain to debug! So don't remove this call!
+        let reqs = vulkan_enable.graphics_requirements(self.xr_instance, self.xr_system)?;
+
+        if self.vulkan_version < reqs.min_api_version_supported
+            || self.vulkan_version.major() > reqs.max_api_version_supported.major()
+        {
+            return Err(Error::UnsupportedVulkanVersion {
+                target: self.vulkan_version,
+                min_supported: reqs.min_api_version_supported,
+                max_supported: reqs.max_api_version_supported,
+            });
+        }
+
+        let vk_entry = unsafe { ash::Entry::load()? };
+        check_available(&vk_entry, &self.extensions, &self.layers)?;
+
+        let vk_target_version = vk_version(self.vulkan_version);
+
+        // Fetch extensions needed by WPGU, and add the requested ones
+        let mut extensions = <Vulkan as Api>::Instance::desired_extensions(
+            &vk_entry,
             vk_target_version,
-            0,
-            None,
-            extensions,
-            flags,
-            MemoryBudgetThresholds::default(),
-            false,
-            None,
-        )?
-    };
+            self.flags,
+        )?;
+        for extension in &self.extensions {
+            if !extensions.contains(extension) {
+                extensions.push(*extension);
+            }
+        }
+
+        // With the original extension the runtime can't add its own extensions, we enable the ones
+        // it lists
+        let runtime_extensions = match vulkan_enable {
+            VulkanEnable::Enable2 => Vec::new(),
+            VulkanEnable::Enable => legacy_instance_extensions(self.xr_instance, self.xr_system)?,
+        };
+
+        let vk_instance = unsafe {
+            self.create_vk_instance(&vk_entry, &extensions, &runtime_extensions, vulkan_enable)?
+        };
+
+        // Create the WPGU instance from the raw instance
+        let hal_instance = unsafe {
+            <Vulkan as Api>::Instance::from_raw(
+                vk_entry,
+                vk_instance,
+                vk_target_version,
+                0,
+                None,
+                extensions.clone(),
+                self.flags,
+                MemoryBudgetThresholds::default(),
+                false,
+                None,
+            )?
+        };
+
+        let instance = unsafe { Instance::from_hal::<Vulkan>(hal_instance) };
+
+        let mut enabled_extensions: Vec<_> = extensions.iter().map(|&name| name.into()).collect();
+        for name in runtime_extensions {
+            if !enabled_extensions.contains(&name) {
+                enabled_extensions.push(name);
+            }
+        }
+        let info = InstanceInfo {
+            vulkan_enable,
+            vulkan_version: self.vulkan_version,
+            extensions: enabled_extensions,
+            layers: self.layers,
+            flags: self.flags,
+        };
+
+        Ok((instance, info))
+    }
 
-    let instance = unsafe { Instance::from_hal::<Vulkan>(hal_instance) };
+    unsafe fn create_vk_instance(
+        &self,
+        vk_entry: &ash::Entry,
+        extensions: &[&'static CStr],
+        runtime_extensions: &[CString],
+        vulkan_enable: VulkanEnable,
+    ) -> Result<ash::Instance, Error> {
+        let mut vk_app_info = vk::ApplicationInfo::default()
+            .application_version(vk_version(self.application_version))
+            .engine_version(vk_version(self.engine_version))
+            .api_version(vk_version(self.vulkan_version));
+        if let Some(name) = self.application_name {
+            vk_app_info = vk_app_info.application_name(name);
+        }
+        if let Some(name) = self.engine_name {
+            vk_app_info = vk_app_info.engine_name(name);
+        }
+
+        let extensions_cchar = merge_extensions(extensions, runtime_extensions);
+        let layers_cchar: Vec<_> = self.layers.iter().map(|s| s.as_ptr()).collect();
+
+        let instance_info = vk::InstanceCreateInfo::default()
+            .application_info(&vk_app_info)
+            .enabled_extension_names(&extensions_cchar)
+            .enabled_layer_names(&layers_cchar);
+
+        if vulkan_enable == VulkanEnable::Enable {
+            let vk_instance = unsafe { vk_entry.create_instance(&instance_info, None)? };
+            return Ok(vk_instance);
+        }
+
+        // Let OpenXR create the instance
+        let get_instance_proc_addr = unsafe {
+            std::mem::transmute::<
+                ash::vk::PFN_vkGetInstanceProcAddr,
+                openxr::sys::platform::VkGetInstanceProcAddr,
+            >(vk_entry.static_fn().get_instance_proc_addr)
+        };
+        let vk_instance = unsafe {
+            self.xr_instance.create_vulkan_instance(
+                self.xr_system,
+                get_instance_proc_addr,
+                &instance_info as *const _ as *const _,
+            )
+        };
+        let vk_instance = vk_instance?.map_err(vk::Result::from_raw)?;
+
+        // Convert to ash instance
+        let vk_instance = unsafe {
+            ash::Instance::load(
+                vk_entry.static_fn(),
+                vk::Instance::from_raw(vk_instance as _),
+            )
+        };
+
+        Ok(vk_instance)
+    }
+}
 
-    Ok(instance)
+/// What was negotiated when creating an instance with `InstanceBuilder`.
+#[derive(Debug, Clone)]
+pub struct InstanceInfo {
+    pub vulkan_enable: VulkanEnable,
+    pub vulkan_version: openxr::Version,
+    /// Instance extensions enabled, the ones WGPU needs, the extra ones, and with
+    /// `XR_KHR_vulkan_enable` the ones the runtime lists.
+    ///
+    /// With `XR_KHR_vulkan_enable2` the runtime creates the instance, and can enable more
+    /// extensions than these without reporting them.
+    pub extensions: Vec<CString>,
+    pub layers: Vec<&'static CStr>,
+    pub flags: InstanceFlags,
 }
 
-unsafe fn create_vk_instance(
-    xr_instance: &openxr::Instance,
-    xr_system: openxr::SystemId,
+fn check_available(
     vk_entry: &ash::Entry,
-    vk_target_version: u32,
-) -> Result<(ash::Instance, Vec<&'static CStr>, InstanceFlags), Error> {
-    let vk_app_info = vk::ApplicationInfo::default()
-        .application_version(0)
-        .engine_version(0)
-        .api_version(vk_target_version);
-
-    // Fetch extensions needed by WPGU
-    let flags = InstanceFlags::empty();
-    let extensions =
-        <Vulkan as Api>::Instance::desired_extensions(vk_entry, vk_target_version, flags)?;
-    let extensions_cchar: Vec<_> = extensions.iter().map(|s| s.as_ptr()).collect();
-
-    let instance_info = vk::InstanceCreateInfo::default()
-        .application_info(&vk_app_info)
-        .enabled_extension_names(&extensions_cchar);
-
-    // Let OpenXR create the instance
-    let get_instance_proc_addr = unsafe {
-        std::mem::transmute::<
-            ash::vk::PFN_vkGetInstanceProcAddr,
-            openxr::sys::platform::VkGetInstanceProcAddr,
-        >(vk_entry.static_fn().get_instance_proc_addr)
-    };
-    let vk_instance = unsafe {
-        xr_instance.create_vulkan_instance(
-            xr_system,
-            get_instance_proc_addr,
-            &instance_info as *const _ as *const _,
-        )
-    };
-    let vk_instance = vk_instance
-        .context("xr error creating vulkan instance")?
-        .map_err(vk::Result::from_raw)
-        .context("vulkan error creating vulkan instance")?;
-
-    // Convert to ash instance
-    let vk_instance = unsafe {
-        ash::Instance::load(
-            vk_entry.static_fn(),
-            vk::Instance::from_raw(vk_instance as _),
-        )
-    };
+    extensions: &[&'static CStr],
+    layers: &[&'static CStr],
+) -> Result<(), Error> {
+    if !extensions.is_empty() {
+        let available = unsafe { vk_entry.enumerate_instance_extension_properties(None)? };
+        let missing: Vec<_> = extensions
+            .iter()
+            .copied()
+            .filter(|name| {
+                !available.iter().any(|properties| {
+                    properties
+                        .extension_name_as_c_str()
+                        .is_ok_and(|n| n == *name)
+                })
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::UnavailableInstanceExtensions(missing));
+        }
+    }
+
+    if !layers.is_empty() {
+        let available = unsafe { vk_entry.enumerate_instance_layer_properties()? };
+        let missing: Vec<_> = layers
+            .iter()
+            .copied()
+            .filter(|name| {
+                !available
+                    .iter()
+                    .any(|properties| properties.layer_name_as_c_str().is_ok_and(|n| n == *name))
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::UnavailableInstanceLayers(missing));
+        }
+    }
+
+    Ok(())
+}
+
+/// Configuration of the WGPU device created by `create_device`.
+///
+/// Features and limits required for XR rendering are always requested, and don't need to be
+/// specified here.
+pub struct DeviceConfig<'a> {
+    pub label: Option<&'a str>,
+    /// Features the device must support, creating the device fails if these aren't available.
+    pub required_features: Features,
+    /// Features that are enabled only if the adapter supports them.
+    pub optional_features: Features,
+    pub required_limits: Limits,
+    pub memory_hints: MemoryHints,
+    pub trace: Trace,
+    /// Device extensions to enable, on top of the ones WGPU needs for the features.
+    ///
+    /// Creating the device fails if the physical device doesn't support these.
+    pub extensions: &'a [&'static CStr],
+    /// Extends the device create info, for example with feature structs of `extensions`.
+    pub create_hook: Option<&'a dyn DeviceCreateHook>,
+}
 
-    Ok((vk_instance, extensions, flags))
+impl Default for DeviceConfig<'_> {
+    fn default() -> Self {
+        Self {
+            label: Some("vr device"),
+            required_features: Features::empty(),
+            optional_features: Features::empty(),
+            required_limits: Limits::default(),
+            memory_hints: MemoryHints::default(),
+            trace: Trace::default(),
+            extensions: &[],
+            create_hook: None,
+        }
+    }
+}
+
+/// Extends the `VkDeviceCreateInfo` that `create_device` has the runtime create the device with.
+///
+/// Implemented for closures with the same signature as `device_create_next`.
+pub trait DeviceCreateHook {
+    /// Structs to add to the create info's pNext chain, like the feature structs of
+    /// `DeviceConfig::extensions`.
+    ///
+    /// WGPU already chains the feature structs it needs for the enabled features, chaining the same
+    /// struct type again is invalid.
+    fn device_create_next(
+        &self,
+        vk_instance: &ash::Instance,
+        vk_physical_device: vk::PhysicalDevice,
+    ) -> Vec<Box<dyn vk::ExtendsDeviceCreateInfo>>;
+}
+
+impl<F> DeviceCreateHook for F
+where
+    F: Fn(&ash::Instance, vk::PhysicalDevice) -> Vec<Box<dyn vk::ExtendsDeviceCreateInfo>>,
+{
+    fn device_create_next(
+        &self,
+        vk_instance: &ash::Instance,
+        vk_physical_device: vk::PhysicalDevice,
+    ) -> Vec<Box<dyn vk::ExtendsDeviceCreateInfo>> {
+        self(vk_instance, vk_physical_device)
+    }
 }
 
+/// Creates the WGPU device for the OpenXR system.
+///
+/// Returns the device, its queue, and which of `config.optional_features` were enabled.
 pub fn create_device(
     xr_instance: &openxr::Instance,
     xr_system: openxr::SystemId,
     instance: &Instance,
-) -> Result<(Device, Queue), Error> {
-    let required_features =
+    config: &DeviceConfig,
+) -> Result<(Device, Queue, Features), Error> {
+    let xr_features =
         // Required for efficiently rendering both sides
         Features::MULTIVIEW |
         // Required for MSAA rendering, we need a texture that's both an array and has multisample
         Features::MULTISAMPLE_ARRAY;
-    let required_limits = Limits {
-        max_multiview_view_count: 2,
-        ..Default::default()
-    };
+    let required_features = xr_features | config.required_features;
+
+    let mut required_limits = config.required_limits.clone();
+    required_limits.max_multiview_view_count = required_limits.max_multiview_view_count.max(2);
 
     let hal_instance = unsafe { instance.as_hal::<Vulkan>() };
-    let hal_instance = hal_instance.context("wgpu instance backend not vulkan")?;
+    let hal_instance = hal_instance.ok_or(Error::NotVulkan)?;
     let shared = hal_instance.shared_instance();
 
+    let vulkan_enable = VulkanEnable::detect(xr_instance)?;
     let vk_physical_device = get_vk_physical_device(
         xr_instance,
         xr_system,
+        vulkan_enable,
         shared.instance_api_version(),
         shared.raw_instance(),
     )?;
@@ -139,35 +407,47 @@ pub fn create_device(
     // Get the WGPU adapter for the picked physical device
     let hal_adapter = hal_instance
         .expose_adapter(vk_physical_device)
-        .context("failed to expose adapter")?;
+        .ok_or(Error::AdapterUnavailable)?;
 
     // Verify all features were exposed by the adapter
     if !hal_adapter.features.contains(required_features) {
         let unsupported = required_features - hal_adapter.features;
-        bail!("unsupported features: {}", unsupported);
+        return Err(Error::UnsupportedFeatures(unsupported));
+    }
+    let enabled_optional_features = config.optional_features & hal_adapter.features;
+    let features = required_features | enabled_optional_features;
+
+    // Verify the adapter can provide the limits we want
+    let mut unsupported_limits = Vec::new();
+    required_limits.check_limits_with_fail_fn(
+        &hal_adapter.capabilities.limits,
+        false,
+        |name, _, _| unsupported_limits.push(name),
+    );
+    if !unsupported_limits.is_empty() {
+        return Err(Error::UnsupportedLimits(unsupported_limits));
     }
 
     let (queue_family_index, device_extensions, vk_device) = unsafe {
-        create_vk_device(
+        let context = DeviceContext {
             xr_instance,
             xr_system,
-            shared.entry(),
-            shared.raw_instance(),
+            vk_entry: shared.entry(),
+            vk_instance: shared.raw_instance(),
             vk_physical_device,
-            &hal_adapter,
-            required_features,
-        )?
+            vulkan_enable,
+        };
+        create_vk_device(&context, &hal_adapter, features, config)?
     };
 
     // Get the WPGU open device for the created device
-    let memory_hints = MemoryHints::default();
     let hal_device = unsafe {
         hal_adapter.adapter.device_from_raw(
             vk_device.clone(),
             None,
             &device_extensions,
-            required_features,
-            &memory_hints,
+            features,
+            &config.memory_hints,
             queue_family_index,
             0,
         )?
@@ -176,50 +456,66 @@ pub fn create_device(
     // Create the WPGU Device handles from all the raw stuff we prepared
     let wgpu_adapter = unsafe { instance.create_adapter_from_hal(hal_adapter) };
     let device_desc = DeviceDescriptor {
-        label: Some("vr device"),
-        required_features,
+        label: config.label,
+        required_features: features,
         required_limits,
         experimental_features: ExperimentalFeatures::default(),
-        memory_hints,
-        trace: Trace::default(),
+        memory_hints: config.memory_hints.clone(),
+        trace: config.trace.clone(),
     };
     let (device, queue) = unsafe { wgpu_adapter.create_device_from_hal(hal_device, &device_desc)? };
 
-    Ok((device, queue))
+    Ok((device, queue, enabled_optional_features))
 }
 
 fn get_vk_physical_device(
     xr_instance: &openxr::Instance,
     xr_system: openxr::SystemId,
+    vulkan_enable: VulkanEnable,
     vk_target_version: u32,
     vk_instance: &ash::Instance,
 ) -> Result<vk::PhysicalDevice, Error> {
-    let vk_physical_device_raw = unsafe {
-        xr_instance.vulkan_graphics_device(xr_system, vk_instance.handle().as_raw() as _)
-    };
-    let vk_physical_device_raw = vk_physical_device_raw
-        .ok()
-        .context("unable to get physical device, runtime may not be running")?;
-    let vk_physical_device = vk::PhysicalDevice::from_raw(vk_physical_device_raw as _);
+    let vk_physical_device =
+        vulkan_enable.physical_device(xr_instance, xr_system, vk_instance.handle())?;
 
     let vk_device_properties =
         unsafe { vk_instance.get_physical_device_properties(vk_physical_device) };
     if vk_device_properties.api_version < vk_target_version {
-        bail!("vulkan physical device doesn't support version 1.2");
+        return Err(Error::UnsupportedDeviceVersion {
+            target: xr_version(vk_target_version),
+            supported: xr_version(vk_device_properties.api_version),
+        });
     }
 
     Ok(vk_physical_device)
 }
 
-unsafe fn create_vk_device(
-    xr_instance: &openxr::Instance,
+/// The system and physical device a Vulkan device is being created for.
+#[derive(Clone, Copy)]
+struct DeviceContext<'a> {
+    xr_instance: &'a openxr::Instance,
     xr_system: openxr::SystemId,
-    vk_entry: &ash::Entry,
-    vk_instance: &ash::Instance,
+    vulkan_enable: VulkanEnable,
+    vk_entry: &'a ash::Entry,
+    vk_instance: &'a ash::Instance,
     vk_physical_device: vk::PhysicalDevice,
+}
+
+unsafe fn create_vk_device(
+    context: &DeviceContext,
     hal_adapter: &ExposedAdapter<Vulkan>,
     features: Features,
+    config: &DeviceConfig,
 ) -> Result<(u32, Vec<&'static CStr>, ash::Device), Error> {
+    let DeviceContext {
+        xr_instance,
+        xr_system,
+        vulkan_enable,
+        vk_entry,
+        vk_instance,
+        vk_physical_device,
+    } = *context;
+
     let queue_family_index = unsafe {
         vk_instance
             .get_physical_device_queue_family_properties(vk_physical_device)
@@ -232,16 +528,34 @@ unsafe fn create_vk_device(
                     None
                 }
             })
-            .context("vulkan device has no graphics queue")?
+            .ok_or(Error::NoGraphicsQueue)?
     };
 
     // Get the device extensions for the request WPGU features
-    let device_extensions = hal_adapter.adapter.required_device_extensions(features);
-    let device_extensions_cchar: Vec<_> = device_extensions.iter().map(|s| s.as_ptr()).collect();
+    let wgpu_extensions = hal_adapter.adapter.required_device_extensions(features);
 
+    // WGPU only chains feature structs for its own extensions, the extra ones are up to the hook
     let mut enabled_physical_device_features = hal_adapter
         .adapter
-        .physical_device_features(&device_extensions, features);
+        .physical_device_features(&wgpu_extensions, features);
+
+    let mut device_extensions = wgpu_extensions;
+    if !config.extensions.is_empty() {
+        check_device_extensions(vk_instance, vk_physical_device, config.extensions)?;
+
+        for extension in config.extensions {
+            if !device_extensions.contains(extension) {
+                device_extensions.push(*extension);
+            }
+        }
+    }
+
+    // The runtime's extensions are enabled on the device, but WGPU doesn't need to know about them
+    let runtime_extensions = match vulkan_enable {
+        VulkanEnable::Enable2 => Vec::new(),
+        VulkanEnable::Enable => legacy_device_extensions(xr_instance, xr_system)?,
+    };
+    let device_extensions_cchar = merge_extensions(&device_extensions, &runtime_extensions);
 
     let queue_info = vk::DeviceQueueCreateInfo::default()
         .queue_family_index(queue_family_index)
@@ -251,7 +565,21 @@ unsafe fn create_vk_device(
     let device_info = vk::DeviceCreateInfo::default()
         .queue_create_infos(&queue_infos)
         .enabled_extension_names(&device_extensions_cchar);
-    let device_info = enabled_physical_device_features.add_to_device_create(device_info);
+    let mut device_info = enabled_physical_device_features.add_to_device_create(device_info);
+
+    let mut hook_next = match config.create_hook {
+        Some(hook) => hook.device_create_next(vk_instance, vk_physical_device),
+        None => Vec::new(),
+    };
+    for next in &mut hook_next {
+        device_info = device_info.push_next(next.as_mut());
+    }
+
+    if vulkan_enable == VulkanEnable::Enable {
+        let vk_device =
+            unsafe { vk_instance.create_device(vk_physical_device, &device_info, None)? };
+        return Ok((queue_family_index, device_extensions, vk_device));
+    }
 
     let get_instance_proc_addr = unsafe {
         std::mem::transmute::<
@@ -268,13 +596,52 @@ unsafe fn create_vk_device(
         )
     };
 
-    let vk_device = vk_device
-        .context("xr error creating vulkan device")?
-        .map_err(vk::Result::from_raw)
-        .context("vulkan error creating vulkan device")?;
+    let vk_device = vk_device?.map_err(vk::Result::from_raw)?;
 
     let vk_device =
         unsafe { ash::Device::load(vk_instance.fp_v1_0(), vk::Device::from_raw(vk_device as _)) };
 
     Ok((queue_family_index, device_extensions, vk_device))
 }
+
+fn check_device_extensions(
+    vk_instance: &ash::Instance,
+    vk_physical_device: vk::PhysicalDevice,
+    extensions: &[&'static CStr],
+) -> Result<(), Error> {
+    let available =
+        unsafe { vk_instance.enumerate_device_extension_properties(vk_physical_device)? };
+    let missing: Vec<_> = extensions
+        .iter()
+        .copied()
+        .filter(|name| {
+            !available.iter().any(|properties| {
+                properties
+                    .extension_name_as_c_str()
+                    .is_ok_and(|n| n == *name)
+            })
+        })
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::UnavailableDeviceExtensions(missing));
+    }
+
+    Ok(())
+}
+
+fn xr_version(vk_version: u32) -> openxr::Version {
+    openxr::Version::new(
+        vk::api_version_major(vk_version) as u16,
+        vk::api_version_minor(vk_version) as u16,
+        vk::api_version_patch(vk_version),
+    )
+}
+
+fn vk_version(xr_version: openxr::Version) -> u32 {
+    vk::make_api_version(
+        0,
+        xr_version.major() as u32,
+        xr_version.minor() as u32,
+        xr_version.patch(),
+    )
+}
@@ -0,0 +1,89 @@
+use openxr::{CompositionLayerProjectionView, sys};
+
+use crate::Swapchain;
+
+/// Depth range of the content rendered into a depth swapchain.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DepthInfoDescriptor {
+    /// Distance of the near plane, as used in the projection matrix.
+    pub near_z: f32,
+    /// Distance of the far plane, as used in the projection matrix.
+    ///
+    /// For reversed-Z projections this is smaller than `near_z`.
+    pub far_z: f32,
+    pub min_depth: f32,
+    pub max_depth: f32,
+}
+
+impl DepthInfoDescriptor {
+    /// Depth info for the full `0.0..=1.0` depth range.
+    pub fn new(near_z: f32, far_z: f32) -> Self {
+        Self {
+            near_z,
+            far_z,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        }
+    }
+}
+
+/// Creates the depth info for every view in a depth swapchain.
+///
+/// View `n` is expected to be in array layer `n` of the swapchain, like how `create_swapchain`
+/// lays out views.
+pub fn create_depth_infos(
+    swapchain: &Swapchain,
+    depth: &DepthInfoDescriptor,
+) -> Vec<sys::CompositionLayerDepthInfoKHR> {
+    let desc = swapchain.desc();
+    let rect = openxr::Rect2Di {
+        offset: openxr::Offset2Di { x: 0, y: 0 },
+        extent: openxr::Extent2Di {
+            width: desc.width as _,
+            height: desc.height as _,
+        },
+    };
+
+    (0..desc.view_count)
+        .map(|view_index| sys::CompositionLayerDepthInfoKHR {
+            ty: sys::CompositionLayerDepthInfoKHR::TYPE,
+            next: std::ptr::null(),
+            sub_image: sys::SwapchainSubImage {
+                swapchain: swapchain.as_raw(),
+                image_rect: rect,
+                image_array_index: view_index,
+            },
+            min_depth: depth.min_depth,
+            max_depth: depth.max_depth,
+            near_z: depth.near_z,
+            far_z: depth.far_z,
+        })
+        .collect()
+}
+
+/// Attaches depth info to projection views, so the runtime can use depth for reprojection.
+///
+/// Depth info is only attached if `XR_KHR_composition_layer_depth` is enabled on the instance, as
+/// the runtime would otherwise reject the frame. Returns if the depth info was attached.
+///
+/// Structs already chained to the views are kept, chained after the depth info.
+pub fn attach_depth_infos<'a>(
+    xr_instance: &openxr::Instance,
+    views: &mut [CompositionLayerProjectionView<'a, openxr::Vulkan>],
+    depth_infos: &'a mut [sys::CompositionLayerDepthInfoKHR],
+) -> bool {
+    if xr_instance.exts().khr_composition_layer_depth.is_none() {
+        return false;
+    }
+
+    for (view, depth_info) in views.iter_mut().zip(depth_infos) {
+        let mut raw = *view.as_raw();
+        depth_info.next = raw.next;
+        raw.next = depth_info as *const _ as *const _;
+
+        // The depth info is borrowed for 'a, same as the swapchain the view already references
+        *view = unsafe { CompositionLayerProjectionView::from_raw(raw) };
+    }
+
+    true
+}
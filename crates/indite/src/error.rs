@@ -0,0 +1,333 @@
+use std::{ffi::CStr, fmt, io, path::PathBuf};
+
+use ash::vk;
+use wgpu::{Features, TextureFormat, hal};
+
+use crate::{ActionType, ReferenceSpaceKind};
+
+/// Error returned by indite.
+///
+/// Errors from the libraries indite wraps are kept as they are, so callers can match on the
+/// underlying result codes, for example to retry when the runtime isn't available yet.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// An OpenXR call failed.
+    Xr(openxr::sys::Result),
+    /// A Vulkan call failed.
+    Vulkan(vk::Result),
+    /// The Vulkan library couldn't be loaded.
+    VulkanLoading(ash::LoadingError),
+    /// WGPU couldn't wrap the Vulkan instance.
+    HalInstance(hal::InstanceError),
+    /// WGPU couldn't wrap the Vulkan device.
+    HalDevice(hal::DeviceError),
+    /// WGPU couldn't create a device from the wrapped Vulkan device.
+    RequestDevice(wgpu::RequestDeviceError),
+    /// Neither `XR_KHR_vulkan_enable2` nor `XR_KHR_vulkan_enable` is enabled on the instance.
+    VulkanNotEnabled,
+    /// A WGPU instance or device doesn't use the Vulkan backend.
+    NotVulkan,
+    /// A WGPU device doesn't use the GL backend.
+    #[cfg(feature = "gles")]
+    NotGles,
+    /// The runtime didn't give a Vulkan physical device, it may not be running.
+    NoPhysicalDevice(openxr::sys::Result),
+    /// The Vulkan version indite targets is outside the range the runtime supports.
+    UnsupportedVulkanVersion {
+        target: openxr::Version,
+        min_supported: openxr::Version,
+        max_supported: openxr::Version,
+    },
+    /// The requested Vulkan version is lower than indite needs.
+    VulkanVersionTooLow {
+        target: openxr::Version,
+        minimum: openxr::Version,
+    },
+    /// Instance extensions that were requested, but aren't available.
+    UnavailableInstanceExtensions(Vec<&'static CStr>),
+    /// Instance layers that were requested, but aren't available.
+    UnavailableInstanceLayers(Vec<&'static CStr>),
+    /// Device extensions that were requested, but the physical device doesn't support.
+    UnavailableDeviceExtensions(Vec<&'static CStr>),
+    /// The physical device doesn't support the Vulkan version indite targets.
+    UnsupportedDeviceVersion {
+        target: openxr::Version,
+        supported: openxr::Version,
+    },
+    /// WGPU doesn't support the physical device the runtime picked.
+    AdapterUnavailable,
+    /// Required features the adapter doesn't support.
+    UnsupportedFeatures(Features),
+    /// Names of required limits the adapter can't provide.
+    UnsupportedLimits(Vec<&'static str>),
+    /// The physical device has no queue family supporting graphics.
+    NoGraphicsQueue,
+    /// OpenXR extensions a feature depends on, which aren't enabled on the instance.
+    MissingExtensions(Vec<&'static str>),
+    /// A Vulkan device extension a feature depends on, which isn't enabled on the device.
+    MissingDeviceExtension(&'static CStr),
+    /// None of the requested swapchain formats are supported by the runtime.
+    UnsupportedSwapchainFormats(Vec<TextureFormat>),
+    /// None of the requested environment blend modes are supported by the runtime.
+    UnsupportedBlendModes(Vec<openxr::EnvironmentBlendMode>),
+    /// None of the requested reference spaces are supported by the runtime.
+    UnsupportedReferenceSpaces(Vec<ReferenceSpaceKind>),
+    /// Cube swapchains need square faces.
+    CubeNotSquare {
+        width: u32,
+        height: u32,
+    },
+    /// Foveation was changed on a swapchain that was created without it.
+    FoveationNotEnabled,
+    /// Foveation was requested on a GL swapchain, which only supports it on Vulkan.
+    #[cfg(feature = "gles")]
+    FoveationUnsupported,
+    /// The swapchain's lock was poisoned by a panic while it was held.
+    SwapchainPoisoned,
+    /// A projection layer was built without views.
+    NoViews,
+    /// A projection layer was given a different number of sub rects than views.
+    SubRectCountMismatch {
+        sub_rects: usize,
+        views: usize,
+    },
+    /// A projection layer has more views than the swapchain has array layers.
+    TooManyViews {
+        views: u32,
+        layers: u32,
+    },
+    /// A layer's image array index is past the swapchain's array layers.
+    ImageArrayIndexOutOfRange {
+        index: u32,
+        layers: u32,
+    },
+    /// A cube layer was given a swapchain not created with `create_cube_swapchain`.
+    NotCubeSwapchain,
+    /// A layer showing colors was given a depth or cube swapchain.
+    NotColorSwapchain,
+    /// An action manifest couldn't be read.
+    ManifestIo {
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// An action manifest file isn't `.toml` or `.json`.
+    UnknownManifestFormat(PathBuf),
+    ManifestToml(toml::de::Error),
+    ManifestJson(serde_json::Error),
+    /// The runtime rejected an action set, action, or interaction profile from the manifest.
+    ActionSetup {
+        name: String,
+        source: openxr::sys::Result,
+    },
+    /// No action with this name, for example one a binding refers to.
+    UnknownAction(String),
+    /// An action was requested as a different type than it was declared as.
+    ActionTypeMismatch {
+        name: String,
+        actual: ActionType,
+        expected: ActionType,
+    },
+    /// `Haptics` was created from actions without the ones `add_haptic_actions` adds.
+    MissingHapticActions,
+}
+
+impl Error {
+    pub(crate) fn missing_extension(name: &'static str) -> Self {
+        Error::MissingExtensions(vec![name])
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Xr(result) => write!(f, "openxr error: {}", result),
+            Error::Vulkan(result) => write!(f, "vulkan error: {}", result),
+            Error::VulkanLoading(_) => write!(f, "failed to load vulkan"),
+            Error::HalInstance(_) => write!(f, "failed to create wgpu instance"),
+            Error::HalDevice(_) => write!(f, "failed to open wgpu device"),
+            Error::RequestDevice(_) => write!(f, "failed to create wgpu device"),
+            Error::VulkanNotEnabled => write!(
+                f,
+                "neither XR_KHR_vulkan_enable2 nor XR_KHR_vulkan_enable is enabled"
+            ),
+            Error::NotVulkan => write!(f, "wgpu backend not vulkan"),
+            #[cfg(feature = "gles")]
+            Error::NotGles => write!(f, "wgpu backend not gl"),
+            Error::NoPhysicalDevice(_) => {
+                write!(
+                    f,
+                    "unable to get physical device, runtime may not be running"
+                )
+            }
+            Error::UnsupportedVulkanVersion {
+                target,
+                min_supported,
+                max_supported,
+            } => write!(
+                f,
+                "openxr runtime requires vulkan version >= {}, <= {}, but {} is targeted",
+                min_supported, max_supported, target
+            ),
+            Error::VulkanVersionTooLow { target, minimum } => write!(
+                f,
+                "vulkan version {} was requested, but at least {} is required",
+                target, minimum
+            ),
+            Error::UnavailableInstanceExtensions(names) => {
+                write!(
+                    f,
+                    "vulkan instance extensions {:?} are not available",
+                    names
+                )
+            }
+            Error::UnavailableInstanceLayers(names) => {
+                write!(f, "vulkan instance layers {:?} are not available", names)
+            }
+            Error::UnavailableDeviceExtensions(names) => {
+                write!(f, "vulkan device extensions {:?} are not available", names)
+            }
+            Error::UnsupportedDeviceVersion { target, supported } => write!(
+                f,
+                "vulkan physical device supports version {}, but {} is required",
+                supported, target
+            ),
+            Error::AdapterUnavailable => write!(f, "failed to expose adapter"),
+            Error::UnsupportedFeatures(features) => {
+                write!(f, "unsupported features: {}", features)
+            }
+            Error::UnsupportedLimits(limits) => {
+                write!(f, "unsupported limits: {}", limits.join(", "))
+            }
+            Error::NoGraphicsQueue => write!(f, "vulkan device has no graphics queue"),
+            Error::MissingExtensions(names) => {
+                let verb = if names.len() == 1 { "is" } else { "are" };
+                write!(f, "{} {} not enabled", names.join(", "), verb)
+            }
+            Error::MissingDeviceExtension(name) => {
+                write!(f, "vulkan device extension {:?} is not enabled", name)
+            }
+            Error::UnsupportedSwapchainFormats(formats) => write!(
+                f,
+                "none of the requested swapchain formats {:?} are supported by the runtime",
+                formats
+            ),
+            Error::UnsupportedBlendModes(modes) => write!(
+                f,
+                "none of the requested environment blend modes {:?} are supported by the runtime",
+                modes
+            ),
+            Error::UnsupportedReferenceSpaces(kinds) => write!(
+                f,
+                "none of the requested reference spaces {:?} are supported by the runtime",
+                kinds
+            ),
+            Error::CubeNotSquare { width, height } => write!(
+                f,
+                "cube swapchain faces must be square, got {}x{}",
+                width, height
+            ),
+            Error::FoveationNotEnabled => write!(
+                f,
+                "swapchain was created without foveation, it can't be applied afterwards"
+            ),
+            #[cfg(feature = "gles")]
+            Error::FoveationUnsupported => write!(f, "foveation is not supported on gl swapchains"),
+            Error::SwapchainPoisoned => write!(f, "failed to lock swapchain"),
+            Error::NoViews => write!(f, "projection layer needs at least one view"),
+            Error::SubRectCountMismatch { sub_rects, views } => {
+                write!(f, "got {} sub rects for {} views", sub_rects, views)
+            }
+            Error::TooManyViews { views, layers } => write!(
+                f,
+                "got {} views, but swapchain only has {} array layers",
+                views, layers
+            ),
+            Error::ImageArrayIndexOutOfRange { index, layers } => write!(
+                f,
+                "image array index {} is out of range, swapchain has {} array layers",
+                index, layers
+            ),
+            Error::NotCubeSwapchain => write!(f, "cube layers need a cube swapchain"),
+            Error::NotColorSwapchain => write!(f, "layer needs a color swapchain"),
+            Error::ManifestIo { path, .. } => {
+                write!(f, "failed to read action manifest {}", path.display())
+            }
+            Error::UnknownManifestFormat(path) => write!(
+                f,
+                "action manifest {} must have a toml or json extension",
+                path.display()
+            ),
+            Error::ManifestToml(_) => write!(f, "failed to parse toml action manifest"),
+            Error::ManifestJson(_) => write!(f, "failed to parse json action manifest"),
+            Error::ActionSetup { name, .. } => write!(f, "failed to set up \"{}\"", name),
+            Error::UnknownAction(name) => write!(f, "no action named \"{}\"", name),
+            Error::ActionTypeMismatch {
+                name,
+                actual,
+                expected,
+            } => write!(
+                f,
+                "action \"{}\" is a {:?} action, not {:?}",
+                name, actual, expected
+            ),
+            Error::MissingHapticActions => write!(
+                f,
+                "haptic actions missing, add them to the manifest with add_haptic_actions"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::VulkanLoading(error) => Some(error),
+            Error::HalInstance(error) => Some(error),
+            Error::HalDevice(error) => Some(error),
+            Error::RequestDevice(error) => Some(error),
+            Error::NoPhysicalDevice(result) => Some(result),
+            Error::ManifestIo { source, .. } => Some(source),
+            Error::ManifestToml(error) => Some(error),
+            Error::ManifestJson(error) => Some(error),
+            Error::ActionSetup { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<openxr::sys::Result> for Error {
+    fn from(result: openxr::sys::Result) -> Self {
+        Error::Xr(result)
+    }
+}
+
+impl From<vk::Result> for Error {
+    fn from(result: vk::Result) -> Self {
+        Error::Vulkan(result)
+    }
+}
+
+impl From<ash::LoadingError> for Error {
+    fn from(error: ash::LoadingError) -> Self {
+        Error::VulkanLoading(error)
+    }
+}
+
+impl From<hal::InstanceError> for Error {
+    fn from(error: hal::InstanceError) -> Self {
+        Error::HalInstance(error)
+    }
+}
+
+impl From<hal::DeviceError> for Error {
+    fn from(error: hal::DeviceError) -> Self {
+        Error::HalDevice(error)
+    }
+}
+
+impl From<wgpu::RequestDeviceError> for Error {
+    fn from(error: wgpu::RequestDeviceError) -> Self {
+        Error::RequestDevice(error)
+    }
+}
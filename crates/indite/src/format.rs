@@ -0,0 +1,276 @@
+use ash::vk;
+use wgpu::{AstcBlock, AstcChannel, TextureFormat};
+
+use crate::Error;
+
+/// Translation table between Vulkan formats and their WGPU equivalents.
+///
+/// Every WGPU format that has a direct Vulkan counterpart is listed exactly once. `Depth24Plus` is
+/// missing on purpose, as it's a virtual format that WGPU resolves to different Vulkan formats
+/// depending on the device.
+#[rustfmt::skip]
+const FORMAT_TABLE: &[(vk::Format, TextureFormat)] = &[
+    // 8-bit formats
+    (vk::Format::R8_UNORM, TextureFormat::R8Unorm),
+    (vk::Format::R8_SNORM, TextureFormat::R8Snorm),
+    (vk::Format::R8_UINT, TextureFormat::R8Uint),
+    (vk::Format::R8_SINT, TextureFormat::R8Sint),
+    // 16-bit formats
+    (vk::Format::R16_UINT, TextureFormat::R16Uint),
+    (vk::Format::R16_SINT, TextureFormat::R16Sint),
+    (vk::Format::R16_UNORM, TextureFormat::R16Unorm),
+    (vk::Format::R16_SNORM, TextureFormat::R16Snorm),
+    (vk::Format::R16_SFLOAT, TextureFormat::R16Float),
+    (vk::Format::R8G8_UNORM, TextureFormat::Rg8Unorm),
+    (vk::Format::R8G8_SNORM, TextureFormat::Rg8Snorm),
+    (vk::Format::R8G8_UINT, TextureFormat::Rg8Uint),
+    (vk::Format::R8G8_SINT, TextureFormat::Rg8Sint),
+    // 32-bit formats
+    (vk::Format::R32_UINT, TextureFormat::R32Uint),
+    (vk::Format::R32_SINT, TextureFormat::R32Sint),
+    (vk::Format::R32_SFLOAT, TextureFormat::R32Float),
+    (vk::Format::R16G16_UINT, TextureFormat::Rg16Uint),
+    (vk::Format::R16G16_SINT, TextureFormat::Rg16Sint),
+    (vk::Format::R16G16_UNORM, TextureFormat::Rg16Unorm),
+    (vk::Format::R16G16_SNORM, TextureFormat::Rg16Snorm),
+    (vk::Format::R16G16_SFLOAT, TextureFormat::Rg16Float),
+    (vk::Format::R8G8B8A8_UNORM, TextureFormat::Rgba8Unorm),
+    (vk::Format::R8G8B8A8_SRGB, TextureFormat::Rgba8UnormSrgb),
+    (vk::Format::R8G8B8A8_SNORM, TextureFormat::Rgba8Snorm),
+    (vk::Format::R8G8B8A8_UINT, TextureFormat::Rgba8Uint),
+    (vk::Format::R8G8B8A8_SINT, TextureFormat::Rgba8Sint),
+    (vk::Format::B8G8R8A8_UNORM, TextureFormat::Bgra8Unorm),
+    (vk::Format::B8G8R8A8_SRGB, TextureFormat::Bgra8UnormSrgb),
+    // Packed 32-bit formats
+    (vk::Format::E5B9G9R9_UFLOAT_PACK32, TextureFormat::Rgb9e5Ufloat),
+    (vk::Format::A2B10G10R10_UINT_PACK32, TextureFormat::Rgb10a2Uint),
+    (vk::Format::A2B10G10R10_UNORM_PACK32, TextureFormat::Rgb10a2Unorm),
+    (vk::Format::B10G11R11_UFLOAT_PACK32, TextureFormat::Rg11b10Ufloat),
+    // 64-bit formats
+    (vk::Format::R64_UINT, TextureFormat::R64Uint),
+    (vk::Format::R32G32_UINT, TextureFormat::Rg32Uint),
+    (vk::Format::R32G32_SINT, TextureFormat::Rg32Sint),
+    (vk::Format::R32G32_SFLOAT, TextureFormat::Rg32Float),
+    (vk::Format::R16G16B16A16_UINT, TextureFormat::Rgba16Uint),
+    (vk::Format::R16G16B16A16_SINT, TextureFormat::Rgba16Sint),
+    (vk::Format::R16G16B16A16_UNORM, TextureFormat::Rgba16Unorm),
+    (vk::Format::R16G16B16A16_SNORM, TextureFormat::Rgba16Snorm),
+    (vk::Format::R16G16B16A16_SFLOAT, TextureFormat::Rgba16Float),
+    // 128-bit formats
+    (vk::Format::R32G32B32A32_UINT, TextureFormat::Rgba32Uint),
+    (vk::Format::R32G32B32A32_SINT, TextureFormat::Rgba32Sint),
+    (vk::Format::R32G32B32A32_SFLOAT, TextureFormat::Rgba32Float),
+    // Depth and stencil formats
+    (vk::Format::S8_UINT, TextureFormat::Stencil8),
+    (vk::Format::D16_UNORM, TextureFormat::Depth16Unorm),
+    (vk::Format::D24_UNORM_S8_UINT, TextureFormat::Depth24PlusStencil8),
+    (vk::Format::D32_SFLOAT, TextureFormat::Depth32Float),
+    (vk::Format::D32_SFLOAT_S8_UINT, TextureFormat::Depth32FloatStencil8),
+    // Multi-planar formats
+    (vk::Format::G8_B8R8_2PLANE_420_UNORM, TextureFormat::NV12),
+    (vk::Format::G10X6_B10X6R10X6_2PLANE_420_UNORM_3PACK16, TextureFormat::P010),
+    // BC compressed formats
+    (vk::Format::BC1_RGBA_UNORM_BLOCK, TextureFormat::Bc1RgbaUnorm),
+    (vk::Format::BC1_RGBA_SRGB_BLOCK, TextureFormat::Bc1RgbaUnormSrgb),
+    (vk::Format::BC2_UNORM_BLOCK, TextureFormat::Bc2RgbaUnorm),
+    (vk::Format::BC2_SRGB_BLOCK, TextureFormat::Bc2RgbaUnormSrgb),
+    (vk::Format::BC3_UNORM_BLOCK, TextureFormat::Bc3RgbaUnorm),
+    (vk::Format::BC3_SRGB_BLOCK, TextureFormat::Bc3RgbaUnormSrgb),
+    (vk::Format::BC4_UNORM_BLOCK, TextureFormat::Bc4RUnorm),
+    (vk::Format::BC4_SNORM_BLOCK, TextureFormat::Bc4RSnorm),
+    (vk::Format::BC5_UNORM_BLOCK, TextureFormat::Bc5RgUnorm),
+    (vk::Format::BC5_SNORM_BLOCK, TextureFormat::Bc5RgSnorm),
+    (vk::Format::BC6H_UFLOAT_BLOCK, TextureFormat::Bc6hRgbUfloat),
+    (vk::Format::BC6H_SFLOAT_BLOCK, TextureFormat::Bc6hRgbFloat),
+    (vk::Format::BC7_UNORM_BLOCK, TextureFormat::Bc7RgbaUnorm),
+    (vk::Format::BC7_SRGB_BLOCK, TextureFormat::Bc7RgbaUnormSrgb),
+    // ETC2 and EAC compressed formats
+    (vk::Format::ETC2_R8G8B8_UNORM_BLOCK, TextureFormat::Etc2Rgb8Unorm),
+    (vk::Format::ETC2_R8G8B8_SRGB_BLOCK, TextureFormat::Etc2Rgb8UnormSrgb),
+    (vk::Format::ETC2_R8G8B8A1_UNORM_BLOCK, TextureFormat::Etc2Rgb8A1Unorm),
+    (vk::Format::ETC2_R8G8B8A1_SRGB_BLOCK, TextureFormat::Etc2Rgb8A1UnormSrgb),
+    (vk::Format::ETC2_R8G8B8A8_UNORM_BLOCK, TextureFormat::Etc2Rgba8Unorm),
+    (vk::Format::ETC2_R8G8B8A8_SRGB_BLOCK, TextureFormat::Etc2Rgba8UnormSrgb),
+    (vk::Format::EAC_R11_UNORM_BLOCK, TextureFormat::EacR11Unorm),
+    (vk::Format::EAC_R11_SNORM_BLOCK, TextureFormat::EacR11Snorm),
+    (vk::Format::EAC_R11G11_UNORM_BLOCK, TextureFormat::EacRg11Unorm),
+    (vk::Format::EAC_R11G11_SNORM_BLOCK, TextureFormat::EacRg11Snorm),
+    // ASTC compressed formats
+    astc(vk::Format::ASTC_4X4_UNORM_BLOCK, AstcBlock::B4x4, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_4X4_SRGB_BLOCK, AstcBlock::B4x4, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_4X4_SFLOAT_BLOCK, AstcBlock::B4x4, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_5X4_UNORM_BLOCK, AstcBlock::B5x4, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_5X4_SRGB_BLOCK, AstcBlock::B5x4, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_5X4_SFLOAT_BLOCK, AstcBlock::B5x4, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_5X5_UNORM_BLOCK, AstcBlock::B5x5, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_5X5_SRGB_BLOCK, AstcBlock::B5x5, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_5X5_SFLOAT_BLOCK, AstcBlock::B5x5, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_6X5_UNORM_BLOCK, AstcBlock::B6x5, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_6X5_SRGB_BLOCK, AstcBlock::B6x5, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_6X5_SFLOAT_BLOCK, AstcBlock::B6x5, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_6X6_UNORM_BLOCK, AstcBlock::B6x6, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_6X6_SRGB_BLOCK, AstcBlock::B6x6, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_6X6_SFLOAT_BLOCK, AstcBlock::B6x6, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_8X5_UNORM_BLOCK, AstcBlock::B8x5, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_8X5_SRGB_BLOCK, AstcBlock::B8x5, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_8X5_SFLOAT_BLOCK, AstcBlock::B8x5, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_8X6_UNORM_BLOCK, AstcBlock::B8x6, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_8X6_SRGB_BLOCK, AstcBlock::B8x6, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_8X6_SFLOAT_BLOCK, AstcBlock::B8x6, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_8X8_UNORM_BLOCK, AstcBlock::B8x8, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_8X8_SRGB_BLOCK, AstcBlock::B8x8, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_8X8_SFLOAT_BLOCK, AstcBlock::B8x8, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_10X5_UNORM_BLOCK, AstcBlock::B10x5, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_10X5_SRGB_BLOCK, AstcBlock::B10x5, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_10X5_SFLOAT_BLOCK, AstcBlock::B10x5, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_10X6_UNORM_BLOCK, AstcBlock::B10x6, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_10X6_SRGB_BLOCK, AstcBlock::B10x6, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_10X6_SFLOAT_BLOCK, AstcBlock::B10x6, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_10X8_UNORM_BLOCK, AstcBlock::B10x8, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_10X8_SRGB_BLOCK, AstcBlock::B10x8, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_10X8_SFLOAT_BLOCK, AstcBlock::B10x8, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_10X10_UNORM_BLOCK, AstcBlock::B10x10, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_10X10_SRGB_BLOCK, AstcBlock::B10x10, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_10X10_SFLOAT_BLOCK, AstcBlock::B10x10, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_12X10_UNORM_BLOCK, AstcBlock::B12x10, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_12X10_SRGB_BLOCK, AstcBlock::B12x10, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_12X10_SFLOAT_BLOCK, AstcBlock::B12x10, AstcChannel::Hdr),
+    astc(vk::Format::ASTC_12X12_UNORM_BLOCK, AstcBlock::B12x12, AstcChannel::Unorm),
+    astc(vk::Format::ASTC_12X12_SRGB_BLOCK, AstcBlock::B12x12, AstcChannel::UnormSrgb),
+    astc(vk::Format::ASTC_12X12_SFLOAT_BLOCK, AstcBlock::B12x12, AstcChannel::Hdr),
+];
+
+const fn astc(
+    vk_format: vk::Format,
+    block: AstcBlock,
+    channel: AstcChannel,
+) -> (vk::Format, TextureFormat) {
+    (vk_format, TextureFormat::Astc { block, channel })
+}
+
+/// Converts a Vulkan format to the equivalent WGPU format, if there is one.
+pub fn vk_format_to_wgpu(format: vk::Format) -> Option<TextureFormat> {
+    FORMAT_TABLE
+        .iter()
+        .find(|(vk_format, _)| *vk_format == format)
+        .map(|(_, format)| *format)
+}
+
+/// Converts a WGPU format to the equivalent Vulkan format, if there is one.
+pub fn wgpu_format_to_vk(format: TextureFormat) -> Option<vk::Format> {
+    FORMAT_TABLE
+        .iter()
+        .find(|(_, wgpu_format)| *wgpu_format == format)
+        .map(|(format, _)| *format)
+}
+
+/// Picks the first format in `preferred` that the runtime supports.
+///
+/// `runtime_formats` is the list given by `Session::enumerate_swapchain_formats`, which the runtime
+/// orders by its own preference. If `preferred` is empty, the first runtime format accepted by
+/// `filter` that has a WGPU equivalent is picked instead.
+pub(crate) fn select_format(
+    runtime_formats: &[vk::Format],
+    preferred: &[TextureFormat],
+    filter: impl Fn(TextureFormat) -> bool,
+) -> Result<(vk::Format, TextureFormat), Error> {
+    let selected = if preferred.is_empty() {
+        runtime_formats.iter().find_map(|vk_format| {
+            let format = vk_format_to_wgpu(*vk_format)?;
+            filter(format).then_some((*vk_format, format))
+        })
+    } else {
+        preferred
+            .iter()
+            .copied()
+            .filter(|format| filter(*format))
+            .find_map(|format| {
+                let vk_format = wgpu_format_to_vk(format)?;
+                runtime_formats
+                    .contains(&vk_format)
+                    .then_some((vk_format, format))
+            })
+    };
+
+    selected.ok_or_else(|| Error::UnsupportedSwapchainFormats(preferred.to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_color(format: TextureFormat) -> bool {
+        !format.is_depth_stencil_format()
+    }
+
+    #[test]
+    fn format_table_pairs_are_unique() {
+        for (i, (vk_format, format)) in FORMAT_TABLE.iter().enumerate() {
+            for (other_vk_format, other_format) in &FORMAT_TABLE[i + 1..] {
+                assert_ne!(vk_format, other_vk_format);
+                assert_ne!(format, other_format);
+            }
+
+            assert_eq!(vk_format_to_wgpu(*vk_format), Some(*format));
+            assert_eq!(wgpu_format_to_vk(*format), Some(*vk_format));
+        }
+    }
+
+    #[test]
+    fn depth24_plus_is_excluded() {
+        assert_eq!(wgpu_format_to_vk(TextureFormat::Depth24Plus), None);
+    }
+
+    #[test]
+    fn preference_order_wins_over_runtime_order() {
+        let runtime_formats = [vk::Format::R8G8B8A8_SRGB, vk::Format::B8G8R8A8_SRGB];
+        let preferred = [TextureFormat::Bgra8UnormSrgb, TextureFormat::Rgba8UnormSrgb];
+
+        let selected = select_format(&runtime_formats, &preferred, is_color).unwrap();
+        assert_eq!(
+            selected,
+            (vk::Format::B8G8R8A8_SRGB, TextureFormat::Bgra8UnormSrgb)
+        );
+    }
+
+    #[test]
+    fn runtime_order_is_used_without_preference() {
+        let runtime_formats = [vk::Format::R8G8B8A8_SRGB, vk::Format::B8G8R8A8_SRGB];
+
+        let selected = select_format(&runtime_formats, &[], is_color).unwrap();
+        assert_eq!(
+            selected,
+            (vk::Format::R8G8B8A8_SRGB, TextureFormat::Rgba8UnormSrgb)
+        );
+    }
+
+    #[test]
+    fn depth_formats_are_filtered_out_of_color_selection() {
+        let runtime_formats = [vk::Format::D32_SFLOAT, vk::Format::R8G8B8A8_UNORM];
+
+        let selected = select_format(&runtime_formats, &[], is_color).unwrap();
+        assert_eq!(
+            selected,
+            (vk::Format::R8G8B8A8_UNORM, TextureFormat::Rgba8Unorm)
+        );
+
+        let preferred = [TextureFormat::Depth32Float, TextureFormat::Rgba8Unorm];
+        let selected = select_format(&runtime_formats, &preferred, is_color).unwrap();
+        assert_eq!(
+            selected,
+            (vk::Format::R8G8B8A8_UNORM, TextureFormat::Rgba8Unorm)
+        );
+    }
+
+    #[test]
+    fn no_common_format_is_unsupported() {
+        let runtime_formats = [vk::Format::R8G8B8A8_UNORM];
+        let preferred = [TextureFormat::Rgba16Float];
+
+        let result = select_format(&runtime_formats, &preferred, is_color);
+        assert!(matches!(
+            result,
+            Err(Error::UnsupportedSwapchainFormats(formats)) if formats == preferred
+        ));
+    }
+}
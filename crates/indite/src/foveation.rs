@@ -0,0 +1,264 @@
+use ash::vk::{self, Handle};
+use openxr::sys::{self, Handle as _};
+use wgpu::{Device, hal::api::Vulkan};
+
+use crate::{Error, cvt};
+
+/// Fixed foveated rendering of a swapchain, using `XR_FB_foveation`.
+///
+/// The runtime provides a fragment density map per swapchain image, and updates it to match the
+/// profile. Render passes only render at reduced density if they attach it, which WGPU render
+/// passes can't do yet. Record those passes with raw Vulkan instead, attaching the map from
+/// `SwapchainImage::fragment_density_map`.
+///
+/// The device needs `VK_EXT_fragment_density_map` in `DeviceConfig::extensions`, and its
+/// `fragmentDensityMap` feature enabled through `DeviceConfig::create_hook`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FoveationDescriptor {
+    pub level: FoveationLevel,
+    /// Vertical offset of the foveated area, in normalized coordinates. Positive moves it up.
+    pub vertical_offset: f32,
+    /// Lets the runtime lower the level when the application is keeping up, up to `level`.
+    pub dynamic: bool,
+}
+
+impl FoveationDescriptor {
+    pub fn new(level: FoveationLevel) -> Self {
+        Self {
+            level,
+            vertical_offset: 0.0,
+            dynamic: false,
+        }
+    }
+}
+
+/// How much the edges of the swapchain are reduced in resolution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoveationLevel {
+    None,
+    Low,
+    Medium,
+    High,
+}
+
+impl FoveationLevel {
+    fn xr_level(self) -> sys::FoveationLevelFB {
+        match self {
+            FoveationLevel::None => sys::FoveationLevelFB::NONE,
+            FoveationLevel::Low => sys::FoveationLevelFB::LOW,
+            FoveationLevel::Medium => sys::FoveationLevelFB::MEDIUM,
+            FoveationLevel::High => sys::FoveationLevelFB::HIGH,
+        }
+    }
+}
+
+/// Fragment density map of a foveated swapchain image, owned by the runtime.
+///
+/// Attach it to a render pass through `vk::RenderPassFragmentDensityMapCreateInfoEXT`, in the
+/// `FRAGMENT_DENSITY_MAP_OPTIMAL_EXT` layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FragmentDensityMap {
+    pub image: vk::Image,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Checks everything foveated swapchains depend on is enabled.
+///
+/// On Vulkan the runtime foveates through a fragment density map, which needs extensions on both
+/// the OpenXR instance and the Vulkan device. The device extension can be enabled with
+/// `DeviceConfig::extensions`.
+pub(crate) fn check_foveation_support(
+    xr_instance: &openxr::Instance,
+    device: &Device,
+) -> Result<(), Error> {
+    let exts = xr_instance.exts();
+    let required = [
+        ("XR_FB_foveation", exts.fb_foveation.is_some()),
+        (
+            "XR_FB_foveation_configuration",
+            exts.fb_foveation_configuration.is_some(),
+        ),
+        ("XR_FB_foveation_vulkan", exts.fb_foveation_vulkan.is_some()),
+        (
+            "XR_FB_swapchain_update_state",
+            exts.fb_swapchain_update_state.is_some(),
+        ),
+    ];
+    let missing: Vec<_> = required
+        .iter()
+        .filter(|(_, enabled)| !enabled)
+        .map(|(name, _)| *name)
+        .collect();
+    if !missing.is_empty() {
+        return Err(Error::MissingExtensions(missing));
+    }
+
+    let hal_device = unsafe { device.as_hal::<Vulkan>().ok_or(Error::NotVulkan)? };
+    let fragment_density_map = ash::ext::fragment_density_map::NAME;
+    if !hal_device
+        .enabled_device_extensions()
+        .contains(&fragment_density_map)
+    {
+        return Err(Error::MissingDeviceExtension(fragment_density_map));
+    }
+
+    Ok(())
+}
+
+/// Creates a swapchain the runtime creates fragment density maps for, so profiles can be applied.
+pub(crate) fn create_foveated_swapchain(
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    info: &openxr::SwapchainCreateInfo<openxr::Vulkan>,
+) -> Result<openxr::Swapchain<openxr::Vulkan>, Error> {
+    let foveation_info = sys::SwapchainCreateInfoFoveationFB {
+        ty: sys::SwapchainCreateInfoFoveationFB::TYPE,
+        next: std::ptr::null_mut(),
+        flags: sys::SwapchainCreateFoveationFlagsFB::FRAGMENT_DENSITY_MAP,
+    };
+
+    // openxr's create_swapchain can't chain structs, so go through the core function directly
+    let raw_info = sys::SwapchainCreateInfo {
+        ty: sys::SwapchainCreateInfo::TYPE,
+        next: &foveation_info as *const _ as *const _,
+        create_flags: info.create_flags,
+        usage_flags: info.usage_flags,
+        format: info.format as _,
+        sample_count: info.sample_count,
+        width: info.width,
+        height: info.height,
+        face_count: info.face_count,
+        array_size: info.array_size,
+        mip_count: info.mip_count,
+    };
+    let mut raw = sys::Swapchain::NULL;
+    unsafe {
+        cvt((xr_session.instance().fp().create_swapchain)(
+            xr_session.as_raw(),
+            &raw_info,
+            &mut raw,
+        ))?;
+    }
+
+    Ok(unsafe { openxr::Swapchain::from_raw(xr_session.clone(), raw) })
+}
+
+/// Applies a foveation profile to a swapchain created with `create_foveated_swapchain`.
+pub(crate) fn apply_foveation(
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    xr_swapchain: sys::Swapchain,
+    foveation: &FoveationDescriptor,
+) -> Result<(), Error> {
+    let exts = xr_session.instance().exts();
+    let fb_foveation = exts
+        .fb_foveation
+        .as_ref()
+        .ok_or_else(|| Error::missing_extension("XR_FB_foveation"))?;
+    let fb_swapchain_update_state = exts
+        .fb_swapchain_update_state
+        .as_ref()
+        .ok_or_else(|| Error::missing_extension("XR_FB_swapchain_update_state"))?;
+
+    let level_info = sys::FoveationLevelProfileCreateInfoFB {
+        ty: sys::FoveationLevelProfileCreateInfoFB::TYPE,
+        next: std::ptr::null_mut(),
+        level: foveation.level.xr_level(),
+        vertical_offset: foveation.vertical_offset,
+        dynamic: if foveation.dynamic {
+            sys::FoveationDynamicFB::LEVEL_ENABLED
+        } else {
+            sys::FoveationDynamicFB::DISABLED
+        },
+    };
+    let profile_info = sys::FoveationProfileCreateInfoFB {
+        ty: sys::FoveationProfileCreateInfoFB::TYPE,
+        next: &level_info as *const _ as *mut _,
+    };
+    let mut profile = sys::FoveationProfileFB::NULL;
+    unsafe {
+        cvt((fb_foveation.create_foveation_profile)(
+            xr_session.as_raw(),
+            &profile_info,
+            &mut profile,
+        ))?;
+    }
+
+    let state = sys::SwapchainStateFoveationFB {
+        ty: sys::SwapchainStateFoveationFB::TYPE,
+        next: std::ptr::null_mut(),
+        flags: sys::SwapchainStateFoveationFlagsFB::EMPTY,
+        profile,
+    };
+    let result = unsafe {
+        cvt((fb_swapchain_update_state.update_swapchain)(
+            xr_swapchain,
+            &state as *const _ as *const sys::SwapchainStateBaseHeaderFB,
+        ))
+    };
+
+    // The swapchain keeps its own copy of the profile's state
+    unsafe { (fb_foveation.destroy_foveation_profile)(profile) };
+
+    result?;
+    Ok(())
+}
+
+/// Gets the fragment density maps of a swapchain created with `create_foveated_swapchain`, by image
+/// index.
+pub(crate) fn enumerate_fragment_density_maps(
+    xr_swapchain: &openxr::Swapchain<openxr::Vulkan>,
+) -> Result<Vec<FragmentDensityMap>, Error> {
+    let enumerate = xr_swapchain.instance().fp().enumerate_swapchain_images;
+
+    let mut count = 0;
+    unsafe {
+        cvt(enumerate(
+            xr_swapchain.as_raw(),
+            0,
+            &mut count,
+            std::ptr::null_mut(),
+        ))?;
+    }
+
+    // openxr's enumerate_images can't chain structs either, the maps are returned through the
+    // foveation structs chained to every image
+    let mut density_maps = vec![
+        sys::SwapchainImageFoveationVulkanFB {
+            ty: sys::SwapchainImageFoveationVulkanFB::TYPE,
+            next: std::ptr::null_mut(),
+            image: 0,
+            width: 0,
+            height: 0,
+        };
+        count as usize
+    ];
+    let mut images: Vec<_> = density_maps
+        .iter_mut()
+        .map(|density_map| sys::SwapchainImageVulkanKHR {
+            ty: sys::SwapchainImageVulkanKHR::TYPE,
+            next: density_map as *mut _ as *mut _,
+            image: 0,
+        })
+        .collect();
+    unsafe {
+        cvt(enumerate(
+            xr_swapchain.as_raw(),
+            count,
+            &mut count,
+            images.as_mut_ptr() as *mut sys::SwapchainImageBaseHeader,
+        ))?;
+    }
+    images.truncate(count as usize);
+    density_maps.truncate(count as usize);
+
+    let density_maps = density_maps
+        .into_iter()
+        .map(|density_map| FragmentDensityMap {
+            image: vk::Image::from_raw(density_map.image),
+            width: density_map.width,
+            height: density_map.height,
+        })
+        .collect();
+
+    Ok(density_maps)
+}
@@ -0,0 +1,145 @@
+use crate::Error;
+
+/// Paces rendering to the runtime's display, one frame at a time.
+///
+/// Wraps the `FrameWaiter` and `FrameStream` of a session, making sure every frame that's begun is
+/// also ended.
+pub struct FrameLoop {
+    frame_waiter: openxr::FrameWaiter,
+    frame_stream: openxr::FrameStream<openxr::Vulkan>,
+    environment_blend_mode: openxr::EnvironmentBlendMode,
+}
+
+impl FrameLoop {
+    pub fn new(
+        frame_waiter: openxr::FrameWaiter,
+        frame_stream: openxr::FrameStream<openxr::Vulkan>,
+        environment_blend_mode: openxr::EnvironmentBlendMode,
+    ) -> Self {
+        Self {
+            frame_waiter,
+            frame_stream,
+            environment_blend_mode,
+        }
+    }
+
+    pub fn environment_blend_mode(&self) -> openxr::EnvironmentBlendMode {
+        self.environment_blend_mode
+    }
+
+    pub fn set_environment_blend_mode(&mut self, mode: openxr::EnvironmentBlendMode) {
+        self.environment_blend_mode = mode;
+    }
+
+    /// Waits until the runtime is ready for the next frame, and begins it.
+    ///
+    /// This blocks until the previous frame is finished displaying. If the runtime doesn't want
+    /// the application to render this frame, the frame is ended without layers, and `None` is
+    /// returned.
+    pub fn begin_frame(&mut self) -> Result<Option<Frame<'_>>, Error> {
+        let frame_state = self.frame_waiter.wait()?;
+
+        // Must be called before any rendering is done!
+        self.frame_stream.begin()?;
+
+        if !frame_state.should_render {
+            self.frame_stream.end(
+                frame_state.predicted_display_time,
+                self.environment_blend_mode,
+                &[],
+            )?;
+            return Ok(None);
+        }
+
+        Ok(Some(Frame {
+            frame_loop: self,
+            frame_state,
+            ended: false,
+        }))
+    }
+}
+
+/// Frame begun by `FrameLoop::begin_frame`.
+///
+/// If the frame is dropped without calling `end_frame`, for example on an early return or a panic,
+/// it's ended without any layers.
+pub struct Frame<'a> {
+    frame_loop: &'a mut FrameLoop,
+    frame_state: openxr::FrameState,
+    ended: bool,
+}
+
+impl Frame<'_> {
+    pub fn state(&self) -> &openxr::FrameState {
+        &self.frame_state
+    }
+
+    /// When the frame is predicted to be displayed. Use this to locate views, controllers, etc.
+    pub fn predicted_display_time(&self) -> openxr::Time {
+        self.frame_state.predicted_display_time
+    }
+
+    /// Blend mode the frame is submitted with, see `ProjectionLayerBuilder::environment_blend_mode`.
+    pub fn environment_blend_mode(&self) -> openxr::EnvironmentBlendMode {
+        self.frame_loop.environment_blend_mode
+    }
+
+    /// Predicted time between this frame and the next one being displayed.
+    pub fn predicted_display_period(&self) -> openxr::Duration {
+        self.frame_state.predicted_display_period
+    }
+
+    /// Ends the frame, telling the runtime what layers to present.
+    pub fn end_frame(
+        mut self,
+        layers: &[&openxr::CompositionLayerBase<'_, openxr::Vulkan>],
+    ) -> Result<(), Error> {
+        // Even if this fails, the runtime will have ended the frame
+        self.ended = true;
+
+        self.frame_loop.frame_stream.end(
+            self.frame_state.predicted_display_time,
+            self.frame_loop.environment_blend_mode,
+            layers,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl Drop for Frame<'_> {
+    fn drop(&mut self) {
+        if self.ended {
+            return;
+        }
+
+        let _ = self.frame_loop.frame_stream.end(
+            self.frame_state.predicted_display_time,
+            self.frame_loop.environment_blend_mode,
+            &[],
+        );
+    }
+}
+
+/// Picks the first blend mode in `preferred` that the runtime supports for `view_type`.
+///
+/// If `preferred` is empty, the runtime's own most preferred blend mode is used.
+pub fn select_environment_blend_mode(
+    xr_instance: &openxr::Instance,
+    xr_system: openxr::SystemId,
+    view_type: openxr::ViewConfigurationType,
+    preferred: &[openxr::EnvironmentBlendMode],
+) -> Result<openxr::EnvironmentBlendMode, Error> {
+    let supported = xr_instance.enumerate_environment_blend_modes(xr_system, view_type)?;
+
+    let selected = if preferred.is_empty() {
+        supported.first().copied()
+    } else {
+        preferred
+            .iter()
+            .copied()
+            .find(|mode| supported.contains(mode))
+    };
+
+    selected.ok_or_else(|| Error::UnsupportedBlendModes(preferred.to_vec()))
+}
@@ -0,0 +1,587 @@
+//! OpenGL and OpenGL ES backend, for runtimes that only provide `XR_KHR_opengl_enable` or
+//! `XR_KHR_opengl_es_enable`.
+//!
+//! Mirrors the Vulkan functions at the crate root, for `openxr::OpenGL` and `openxr::OpenGlEs`
+//! sessions. The runtime can't create a GL context for us, so the application creates one the
+//! runtime accepts, makes it current, and passes its function loader to `create_device`. The
+//! session is then created from the same context.
+//!
+//! The rest of indite, like `FrameLoop`, `SessionDriver` and the layer builders, only supports
+//! Vulkan sessions for now.
+//!
+//! WGPU's GL backend stores images top row first, while OpenXR expects GL images bottom row first,
+//! so flip rendering vertically, for example by negating the Y scale of the projection.
+
+use std::{
+    ffi::c_void,
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+};
+
+use wgpu::{
+    Backends, Device, DeviceDescriptor, ExperimentalFeatures, Extent3d, Features, Instance,
+    InstanceDescriptor, Queue, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureUses, TextureView, TextureViewDescriptor, TextureViewDimension,
+    hal::{self, Adapter as _, api::Gles},
+};
+
+use crate::{
+    DeviceConfig, Error, ImageAcquired, SwapchainDescriptor, SwapchainHandle, SwapchainImage,
+    swapchain::acquire_image,
+};
+
+/// Creates the WGPU instance and device for the GL context current on this thread.
+///
+/// Unlike the Vulkan `create_device`, multiview isn't required, as GL drivers often don't support
+/// it. Request `Features::MULTIVIEW` through `config` to render both views in one pass, or render
+/// every view to its own array layer separately otherwise. The Vulkan specific fields of `config`
+/// are ignored.
+///
+/// Returns the instance, device, its queue, and which of `config.optional_features` were enabled.
+///
+/// # Safety
+/// - The context must be current, and be one `G`'s session create info can refer to.
+/// - `get_proc_address` must load functions of that context.
+pub unsafe fn create_device<G: openxr::Graphics>(
+    xr_instance: &openxr::Instance,
+    xr_system: openxr::SystemId,
+    get_proc_address: impl FnMut(&str) -> *const c_void,
+    config: &DeviceConfig,
+) -> Result<(Instance, Device, Queue, Features), Error> {
+    // Like with Vulkan, the runtime requires this call before creating a session
+    xr_instance.graphics_requirements::<G>(xr_system)?;
+
+    unsafe { open_device(get_proc_address, config) }
+}
+
+/// Creates the device for the current context, without involving the runtime.
+///
+/// # Safety
+/// See `create_device`.
+unsafe fn open_device(
+    get_proc_address: impl FnMut(&str) -> *const c_void,
+    config: &DeviceConfig,
+) -> Result<(Instance, Device, Queue, Features), Error> {
+    let hal_adapter = unsafe {
+        hal::gles::Adapter::new_external(get_proc_address, wgpu::GlBackendOptions::default())
+    };
+    let hal_adapter = hal_adapter.ok_or(Error::AdapterUnavailable)?;
+
+    // Verify all features were exposed by the adapter
+    if !hal_adapter.features.contains(config.required_features) {
+        let unsupported = config.required_features - hal_adapter.features;
+        return Err(Error::UnsupportedFeatures(unsupported));
+    }
+    let enabled_optional_features = config.optional_features & hal_adapter.features;
+    let features = config.required_features | enabled_optional_features;
+
+    // Verify the adapter can provide the limits we want
+    let mut unsupported_limits = Vec::new();
+    config.required_limits.check_limits_with_fail_fn(
+        &hal_adapter.capabilities.limits,
+        false,
+        |name, _, _| unsupported_limits.push(name),
+    );
+    if !unsupported_limits.is_empty() {
+        return Err(Error::UnsupportedLimits(unsupported_limits));
+    }
+
+    let hal_device = unsafe {
+        hal_adapter
+            .adapter
+            .open(features, &config.required_limits, &config.memory_hints)?
+    };
+
+    // The adapter already wraps the application's context, the instance only has to own it. With
+    // the GL backend enabled the instance would open an EGL display of its own, unrelated to that
+    // context.
+    let instance = Instance::new(&InstanceDescriptor {
+        backends: Backends::empty(),
+        ..Default::default()
+    });
+    let wgpu_adapter = unsafe { instance.create_adapter_from_hal(hal_adapter) };
+    let device_desc = DeviceDescriptor {
+        label: config.label,
+        required_features: features,
+        required_limits: config.required_limits.clone(),
+        experimental_features: ExperimentalFeatures::default(),
+        memory_hints: config.memory_hints.clone(),
+        trace: config.trace.clone(),
+    };
+    let (device, queue) = unsafe { wgpu_adapter.create_device_from_hal(hal_device, &device_desc)? };
+
+    Ok((instance, device, queue, enabled_optional_features))
+}
+
+/// Creates the OpenXR session for a device from `create_device`.
+///
+/// # Safety
+/// `info` must refer to the context the device was created from.
+pub unsafe fn create_session<G: openxr::Graphics>(
+    xr_instance: &openxr::Instance,
+    xr_system: openxr::SystemId,
+    info: &G::SessionCreateInfo,
+    device: &Device,
+) -> Result<
+    (
+        openxr::Session<G>,
+        openxr::FrameWaiter,
+        openxr::FrameStream<G>,
+    ),
+    Error,
+> {
+    // Keep dependencies alive
+    let guard = Box::new(device.clone());
+
+    let (xr_session, xr_frame_wait, xr_frame_stream) =
+        unsafe { xr_instance.create_session_with_guard::<G>(xr_system, info, guard)? };
+
+    Ok((xr_session, xr_frame_wait, xr_frame_stream))
+}
+
+/// OpenXR swapchain of GL textures, with a WGPU texture for each of its images.
+pub struct Swapchain<G: openxr::Graphics + Send + 'static> {
+    handle: SwapchainHandle<G>,
+    desc: SwapchainDescriptor,
+    format: TextureFormat,
+    textures: Vec<(Texture, TextureView)>,
+}
+
+impl<G> Swapchain<G>
+where
+    G: openxr::Graphics<Format = u32, SwapchainImage = u32> + Send + 'static,
+{
+    pub fn handle(&self) -> &SwapchainHandle<G> {
+        &self.handle
+    }
+
+    pub fn desc(&self) -> &SwapchainDescriptor {
+        &self.desc
+    }
+
+    /// Format negotiated with the runtime, create pipelines rendering to this swapchain with it.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    /// Textures for every image in the swapchain, by image index.
+    pub fn textures(&self) -> &[(Texture, TextureView)] {
+        &self.textures
+    }
+
+    /// Acquires the next image from the runtime, see the Vulkan `Swapchain::acquire`.
+    pub fn acquire(&mut self) -> Result<SwapchainImage<'_, ImageAcquired, G>, Error> {
+        acquire_image(&self.handle, &self.textures, &[])
+    }
+}
+
+/// Creates a color swapchain for a GL session, see the Vulkan `create_swapchain`.
+///
+/// Foveation isn't supported on GL.
+pub fn create_swapchain<G>(
+    device: &Device,
+    xr_session: &openxr::Session<G>,
+    desc: &SwapchainDescriptor,
+) -> Result<Swapchain<G>, Error>
+where
+    G: openxr::Graphics<Format = u32, SwapchainImage = u32> + Send + 'static,
+{
+    create_swapchain_with_usage(device, xr_session, desc, false)
+}
+
+/// Creates a depth swapchain for a GL session, see the Vulkan `create_depth_swapchain`.
+pub fn create_depth_swapchain<G>(
+    device: &Device,
+    xr_session: &openxr::Session<G>,
+    desc: &SwapchainDescriptor,
+) -> Result<Swapchain<G>, Error>
+where
+    G: openxr::Graphics<Format = u32, SwapchainImage = u32> + Send + 'static,
+{
+    create_swapchain_with_usage(device, xr_session, desc, true)
+}
+
+fn create_swapchain_with_usage<G>(
+    device: &Device,
+    xr_session: &openxr::Session<G>,
+    desc: &SwapchainDescriptor,
+    depth: bool,
+) -> Result<Swapchain<G>, Error>
+where
+    G: openxr::Graphics<Format = u32, SwapchainImage = u32> + Send + 'static,
+{
+    if desc.foveation.is_some() {
+        return Err(Error::FoveationUnsupported);
+    }
+
+    let default_formats: &[TextureFormat] = if depth {
+        &[
+            TextureFormat::Depth24PlusStencil8,
+            TextureFormat::Depth32Float,
+        ]
+    } else {
+        &[]
+    };
+    let preferred = if desc.formats.is_empty() {
+        default_formats
+    } else {
+        &desc.formats
+    };
+
+    let runtime_formats = xr_session.enumerate_swapchain_formats()?;
+    let accepts = |format: TextureFormat| format.is_depth_stencil_format() == depth;
+    let selected = if preferred.is_empty() {
+        runtime_formats.iter().find_map(|&gl_format| {
+            let format = gl_format_to_wgpu(gl_format)?;
+            accepts(format).then_some((gl_format, format))
+        })
+    } else {
+        preferred
+            .iter()
+            .copied()
+            .filter(|&format| accepts(format))
+            .find_map(|format| {
+                let gl_format = wgpu_format_to_gl(format)?;
+                runtime_formats
+                    .contains(&gl_format)
+                    .then_some((gl_format, format))
+            })
+    };
+    let Some((gl_format, format)) = selected else {
+        return Err(Error::UnsupportedSwapchainFormats(preferred.to_vec()));
+    };
+
+    let xr_usage = if depth {
+        openxr::SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT
+    } else {
+        openxr::SwapchainUsageFlags::COLOR_ATTACHMENT | openxr::SwapchainUsageFlags::SAMPLED
+    };
+    let xr_swapchain = xr_session.create_swapchain(&openxr::SwapchainCreateInfo {
+        create_flags: openxr::SwapchainCreateFlags::EMPTY,
+        usage_flags: xr_usage,
+        format: gl_format,
+        sample_count: 1,
+        width: desc.width,
+        height: desc.height,
+        face_count: 1,
+        array_size: desc.view_count,
+        mip_count: 1,
+    })?;
+    let images = xr_swapchain.enumerate_images()?;
+    let handle = Arc::new(Mutex::new(xr_swapchain));
+
+    let hal_device = unsafe { device.as_hal::<Gles>().ok_or(Error::NotGles)? };
+    let textures = images
+        .into_iter()
+        .map(|image| {
+            // The runtime owns the texture, WGPU mustn't delete it. This also keeps the swapchain
+            // alive.
+            let handle = handle.clone();
+            let drop_callback = Box::new(move || drop(handle));
+
+            let texture = unsafe {
+                create_swapchain_texture(
+                    device,
+                    &hal_device,
+                    desc,
+                    format,
+                    depth,
+                    image,
+                    drop_callback,
+                )?
+            };
+            let view = texture.create_view(&TextureViewDescriptor {
+                dimension: Some(TextureViewDimension::D2Array),
+                array_layer_count: Some(desc.view_count),
+                ..Default::default()
+            });
+            Ok((texture, view))
+        })
+        .collect::<Result<_, Error>>()?;
+
+    Ok(Swapchain {
+        handle,
+        desc: desc.clone(),
+        format,
+        textures,
+    })
+}
+
+/// # Safety
+/// - `image` must be a texture name of the device's context, valid for the information in `desc`
+///   and `format`.
+/// - `image` must stay valid until `drop_callback` is called.
+unsafe fn create_swapchain_texture(
+    device: &Device,
+    hal_device: &hal::gles::Device,
+    desc: &SwapchainDescriptor,
+    format: TextureFormat,
+    depth: bool,
+    image: u32,
+    drop_callback: hal::DropCallback,
+) -> Result<Texture, Error> {
+    // Zero is never a valid texture name, the runtime is misbehaving if it returns it
+    let name =
+        NonZeroU32::new(image).ok_or(Error::Xr(openxr::sys::Result::ERROR_RUNTIME_FAILURE))?;
+
+    let size = Extent3d {
+        width: desc.width,
+        height: desc.height,
+        depth_or_array_layers: desc.view_count,
+    };
+    let (hal_usage, usage) = if depth {
+        (
+            TextureUses::DEPTH_STENCIL_READ | TextureUses::DEPTH_STENCIL_WRITE,
+            TextureUsages::RENDER_ATTACHMENT,
+        )
+    } else {
+        (
+            TextureUses::COLOR_TARGET | TextureUses::COPY_DST,
+            TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_DST,
+        )
+    };
+
+    let hal_texture_desc = hal::TextureDescriptor {
+        label: Some("openxr swapchain texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage: hal_usage,
+        memory_flags: hal::MemoryFlags::empty(),
+        view_formats: Vec::new(),
+    };
+
+    let hal_texture =
+        unsafe { hal_device.texture_from_raw(name, &hal_texture_desc, Some(drop_callback)) };
+
+    let texture_desc = TextureDescriptor {
+        label: Some("openxr swapchain texture"),
+        size,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: TextureDimension::D2,
+        format,
+        usage,
+        view_formats: &[],
+    };
+    Ok(unsafe { device.create_texture_from_hal::<Gles>(hal_texture, &texture_desc) })
+}
+
+/// Converts a GL sized internal format to the matching WGPU format.
+pub fn gl_format_to_wgpu(gl_format: u32) -> Option<TextureFormat> {
+    let format = match gl_format {
+        GL_RGBA8 => TextureFormat::Rgba8Unorm,
+        GL_SRGB8_ALPHA8 => TextureFormat::Rgba8UnormSrgb,
+        GL_RGB10_A2 => TextureFormat::Rgb10a2Unorm,
+        GL_RGBA16F => TextureFormat::Rgba16Float,
+        GL_R11F_G11F_B10F => TextureFormat::Rg11b10Ufloat,
+        GL_DEPTH_COMPONENT16 => TextureFormat::Depth16Unorm,
+        GL_DEPTH_COMPONENT32F => TextureFormat::Depth32Float,
+        GL_DEPTH24_STENCIL8 => TextureFormat::Depth24PlusStencil8,
+        GL_DEPTH32F_STENCIL8 => TextureFormat::Depth32FloatStencil8,
+        _ => return None,
+    };
+
+    Some(format)
+}
+
+/// Converts a WGPU format to the matching GL sized internal format.
+pub fn wgpu_format_to_gl(format: TextureFormat) -> Option<u32> {
+    let gl_format = match format {
+        TextureFormat::Rgba8Unorm => GL_RGBA8,
+        TextureFormat::Rgba8UnormSrgb => GL_SRGB8_ALPHA8,
+        TextureFormat::Rgb10a2Unorm => GL_RGB10_A2,
+        TextureFormat::Rgba16Float => GL_RGBA16F,
+        TextureFormat::Rg11b10Ufloat => GL_R11F_G11F_B10F,
+        TextureFormat::Depth16Unorm => GL_DEPTH_COMPONENT16,
+        TextureFormat::Depth32Float => GL_DEPTH_COMPONENT32F,
+        TextureFormat::Depth24PlusStencil8 => GL_DEPTH24_STENCIL8,
+        TextureFormat::Depth32FloatStencil8 => GL_DEPTH32F_STENCIL8,
+        _ => return None,
+    };
+
+    Some(gl_format)
+}
+
+const GL_RGBA8: u32 = 0x8058;
+const GL_SRGB8_ALPHA8: u32 = 0x8C43;
+const GL_RGB10_A2: u32 = 0x8059;
+const GL_RGBA16F: u32 = 0x881A;
+const GL_R11F_G11F_B10F: u32 = 0x8C3A;
+const GL_DEPTH_COMPONENT16: u32 = 0x81A5;
+const GL_DEPTH_COMPONENT32F: u32 = 0x8CAC;
+const GL_DEPTH24_STENCIL8: u32 = 0x88F0;
+const GL_DEPTH32F_STENCIL8: u32 = 0x8CAD;
+
+#[cfg(test)]
+mod tests {
+    use glow::HasContext;
+    use khronos_egl as egl;
+
+    use super::*;
+
+    const GL_FORMATS: &[u32] = &[
+        GL_RGBA8,
+        GL_SRGB8_ALPHA8,
+        GL_RGB10_A2,
+        GL_RGBA16F,
+        GL_R11F_G11F_B10F,
+        GL_DEPTH_COMPONENT16,
+        GL_DEPTH_COMPONENT32F,
+        GL_DEPTH24_STENCIL8,
+        GL_DEPTH32F_STENCIL8,
+    ];
+
+    const EGL_PLATFORM_SURFACELESS_MESA: egl::Enum = 0x31DD;
+
+    #[test]
+    fn formats_round_trip() {
+        for &gl_format in GL_FORMATS {
+            let format = gl_format_to_wgpu(gl_format).unwrap();
+            assert_eq!(wgpu_format_to_gl(format), Some(gl_format));
+        }
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        assert_eq!(gl_format_to_wgpu(0), None);
+        assert_eq!(wgpu_format_to_gl(TextureFormat::Bgra8Unorm), None);
+    }
+
+    /// Renders to a GL texture wrapped like a swapchain image, on a surfaceless EGL context such as
+    /// Mesa's llvmpipe.
+    #[test]
+    #[ignore = "needs EGL with surfaceless display support, like Mesa's llvmpipe"]
+    fn render_to_swapchain_texture() {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_5>::load_required() }
+            .expect("failed to load EGL 1.5");
+        let display = unsafe {
+            egl.get_platform_display(
+                EGL_PLATFORM_SURFACELESS_MESA,
+                egl::DEFAULT_DISPLAY,
+                &[egl::ATTRIB_NONE],
+            )
+        }
+        .expect("surfaceless EGL display unavailable");
+        egl.initialize(display).unwrap();
+
+        egl.bind_api(egl::OPENGL_ES_API).unwrap();
+        let config = egl
+            .choose_first_config(
+                display,
+                &[
+                    egl::RENDERABLE_TYPE,
+                    egl::OPENGL_ES3_BIT,
+                    // Surfaceless displays have no window configs, which is the default
+                    egl::SURFACE_TYPE,
+                    0,
+                    egl::NONE,
+                ],
+            )
+            .unwrap()
+            .expect("no GLES 3 config");
+        let context = egl
+            .create_context(
+                display,
+                config,
+                None,
+                &[egl::CONTEXT_CLIENT_VERSION, 3, egl::NONE],
+            )
+            .unwrap();
+        egl.make_current(display, None, None, Some(context))
+            .unwrap();
+
+        let get_proc_address = |name: &str| {
+            egl.get_proc_address(name)
+                .map_or(std::ptr::null(), |address| address as *const c_void)
+        };
+        let gl = unsafe { glow::Context::from_loader_function(get_proc_address) };
+        let (_instance, device, queue, _) =
+            unsafe { open_device(get_proc_address, &DeviceConfig::default()).unwrap() };
+
+        // Stand in for the runtime, which would create the swapchain images
+        let desc = SwapchainDescriptor {
+            width: 4,
+            height: 4,
+            view_count: 2,
+            formats: Vec::new(),
+            foveation: None,
+        };
+        let image = unsafe {
+            let image = gl.create_texture().unwrap();
+            gl.bind_texture(glow::TEXTURE_2D_ARRAY, Some(image));
+            gl.tex_storage_3d(glow::TEXTURE_2D_ARRAY, 1, GL_RGBA8, 4, 4, 2);
+            image
+        };
+
+        let texture = unsafe {
+            let hal_device = device.as_hal::<Gles>().unwrap();
+            create_swapchain_texture(
+                &device,
+                &hal_device,
+                &desc,
+                TextureFormat::Rgba8Unorm,
+                false,
+                image.0.get(),
+                Box::new(|| {}),
+            )
+            .unwrap()
+        };
+        let view = texture.create_view(&TextureViewDescriptor {
+            dimension: Some(TextureViewDimension::D2),
+            base_array_layer: 1,
+            array_layer_count: Some(1),
+            ..Default::default()
+        });
+
+        let mut encoder = device.create_command_encoder(&Default::default());
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::RED),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            ..Default::default()
+        });
+        queue.submit([encoder.finish()]);
+        device.poll(wgpu::PollType::wait_indefinitely()).unwrap();
+        drop(view);
+        drop(texture);
+
+        let mut pixel = [0u8; 4];
+        unsafe {
+            let framebuffer = gl.create_framebuffer().unwrap();
+            gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_layer(
+                glow::READ_FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                Some(image),
+                0,
+                1,
+            );
+            gl.read_pixels(
+                0,
+                0,
+                1,
+                1,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelPackData::Slice(Some(&mut pixel)),
+            );
+            gl.delete_framebuffer(framebuffer);
+            gl.delete_texture(image);
+        }
+        assert_eq!(pixel, [255, 0, 0, 255]);
+
+        drop((device, queue));
+        egl.make_current(display, None, None, None).unwrap();
+        egl.destroy_context(display, context).unwrap();
+        egl.terminate(display).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use bytemuck::{Pod, Zeroable};
+use wgpu::{Buffer, BufferDescriptor, BufferUsages, Device, Queue};
+
+use crate::{Error, Hand, TrackedPose};
+
+/// Joints tracked per hand, see `openxr::HandJoint` for their order.
+pub const HAND_JOINT_COUNT: usize = openxr::HAND_JOINT_COUNT;
+
+/// Size in bytes of a buffer holding the joints of both hands, see `GpuHandJoint`.
+pub const HAND_JOINTS_BUFFER_SIZE: u64 = (2 * HAND_JOINT_COUNT * size_of::<GpuHandJoint>()) as u64;
+
+/// Articulated hand tracking, using `XR_EXT_hand_tracking`.
+pub struct HandTracking {
+    left: openxr::HandTracker,
+    right: openxr::HandTracker,
+}
+
+impl HandTracking {
+    /// Creates trackers for both hands.
+    ///
+    /// Returns `None` if the system doesn't support hand tracking. Fails if
+    /// `XR_EXT_hand_tracking` isn't enabled on the instance.
+    pub fn new(
+        xr_instance: &openxr::Instance,
+        xr_system: openxr::SystemId,
+        xr_session: &openxr::Session<openxr::Vulkan>,
+    ) -> Result<Option<Self>, Error> {
+        if xr_instance.exts().ext_hand_tracking.is_none() {
+            return Err(Error::missing_extension("XR_EXT_hand_tracking"));
+        }
+
+        // The extension being enabled doesn't mean the system can actually track hands
+        if !xr_instance.supports_hand_tracking(xr_system)? {
+            return Ok(None);
+        }
+
+        Ok(Some(Self {
+            left: xr_session.create_hand_tracker(openxr::Hand::LEFT)?,
+            right: xr_session.create_hand_tracker(openxr::Hand::RIGHT)?,
+        }))
+    }
+
+    /// Locates the joints of both hands in `base_space` at `time`.
+    pub fn locate(
+        &self,
+        base_space: &openxr::Space,
+        time: openxr::Time,
+    ) -> Result<HandJoints, Error> {
+        Ok(HandJoints {
+            left: locate_hand(&self.left, base_space, time)?,
+            right: locate_hand(&self.right, base_space, time)?,
+        })
+    }
+}
+
+fn locate_hand(
+    tracker: &openxr::HandTracker,
+    base_space: &openxr::Space,
+    time: openxr::Time,
+) -> Result<Option<[HandJoint; HAND_JOINT_COUNT]>, Error> {
+    let Some((locations, velocities)) = base_space.relate_hand_joints(tracker, time)? else {
+        return Ok(None);
+    };
+
+    let joints = std::array::from_fn(|index| {
+        let location = &locations[index];
+        let velocity = &velocities[index];
+        let flags = velocity.velocity_flags;
+
+        HandJoint {
+            location: TrackedPose::new(location.pose, location.location_flags),
+            radius: location.radius,
+            linear_velocity: flags
+                .contains(openxr::SpaceVelocityFlags::LINEAR_VALID)
+                .then_some(velocity.linear_velocity),
+            angular_velocity: flags
+                .contains(openxr::SpaceVelocityFlags::ANGULAR_VALID)
+                .then_some(velocity.angular_velocity),
+        }
+    });
+
+    Ok(Some(joints))
+}
+
+/// Joints of both hands, `None` for hands that aren't currently tracked.
+#[derive(Debug, Clone)]
+pub struct HandJoints {
+    pub left: Option<[HandJoint; HAND_JOINT_COUNT]>,
+    pub right: Option<[HandJoint; HAND_JOINT_COUNT]>,
+}
+
+impl HandJoints {
+    pub fn hand(&self, hand: Hand) -> Option<&[HandJoint; HAND_JOINT_COUNT]> {
+        match hand {
+            Hand::Left => self.left.as_ref(),
+            Hand::Right => self.right.as_ref(),
+        }
+    }
+
+    /// Converts the joints to their GPU layout, left hand first.
+    ///
+    /// Joints of hands that aren't tracked are zeroed, so all their flags are unset.
+    pub fn to_gpu(&self) -> [GpuHandJoint; 2 * HAND_JOINT_COUNT] {
+        let mut gpu_joints = [GpuHandJoint::zeroed(); 2 * HAND_JOINT_COUNT];
+
+        let hands = [self.left.as_ref(), self.right.as_ref()];
+        for (hand, gpu_hand) in hands
+            .into_iter()
+            .zip(gpu_joints.chunks_mut(HAND_JOINT_COUNT))
+        {
+            let Some(joints) = hand else {
+                continue;
+            };
+
+            for (joint, gpu_joint) in joints.iter().zip(gpu_hand) {
+                *gpu_joint = GpuHandJoint::from_joint(joint);
+            }
+        }
+
+        gpu_joints
+    }
+
+    /// Writes the joints to a buffer at `offset`, in the layout of `to_gpu`.
+    pub fn write_buffer(&self, queue: &Queue, buffer: &Buffer, offset: u64) {
+        queue.write_buffer(buffer, offset, bytemuck::cast_slice(&self.to_gpu()));
+    }
+}
+
+/// Joint of a tracked hand.
+#[derive(Debug, Clone, Copy)]
+pub struct HandJoint {
+    pub location: TrackedPose,
+    /// Radius of the joint in meters, roughly the distance to the skin.
+    pub radius: f32,
+    pub linear_velocity: Option<openxr::Vector3f>,
+    pub angular_velocity: Option<openxr::Vector3f>,
+}
+
+/// Hand joint as laid out in GPU buffers, 64 bytes with 16 byte alignment.
+///
+/// Matches this WGSL struct, with the joints of both hands in an
+/// `array<HandJoint, 52>`, left hand first:
+///
+/// ```wgsl
+/// struct HandJoint {
+///     position: vec3<f32>,
+///     radius: f32,
+///     // Quaternion, as x, y, z, w
+///     orientation: vec4<f32>,
+///     linear_velocity: vec3<f32>,
+///     flags: u32,
+///     angular_velocity: vec3<f32>,
+/// }
+/// ```
+///
+/// See the `GpuHandJoint::*` constants for the bits in `flags`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct GpuHandJoint {
+    pub position: [f32; 3],
+    pub radius: f32,
+    pub orientation: [f32; 4],
+    pub linear_velocity: [f32; 3],
+    pub flags: u32,
+    pub angular_velocity: [f32; 3],
+    pub _padding: u32,
+}
+
+impl GpuHandJoint {
+    pub const POSITION_VALID: u32 = 1 << 0;
+    pub const ORIENTATION_VALID: u32 = 1 << 1;
+    pub const POSITION_TRACKED: u32 = 1 << 2;
+    pub const ORIENTATION_TRACKED: u32 = 1 << 3;
+    pub const LINEAR_VELOCITY_VALID: u32 = 1 << 4;
+    pub const ANGULAR_VELOCITY_VALID: u32 = 1 << 5;
+
+    fn from_joint(joint: &HandJoint) -> Self {
+        let location = &joint.location;
+        let position = location.pose.position;
+        let orientation = location.pose.orientation;
+        let linear_velocity = joint.linear_velocity.unwrap_or_default();
+        let angular_velocity = joint.angular_velocity.unwrap_or_default();
+
+        let flags = [
+            (location.position_valid, Self::POSITION_VALID),
+            (location.orientation_valid, Self::ORIENTATION_VALID),
+            (location.position_tracked, Self::POSITION_TRACKED),
+            (location.orientation_tracked, Self::ORIENTATION_TRACKED),
+            (joint.linear_velocity.is_some(), Self::LINEAR_VELOCITY_VALID),
+            (
+                joint.angular_velocity.is_some(),
+                Self::ANGULAR_VELOCITY_VALID,
+            ),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .fold(0, |flags, (_, bit)| flags | bit);
+
+        Self {
+            position: [position.x, position.y, position.z],
+            radius: joint.radius,
+            orientation: [orientation.x, orientation.y, orientation.z, orientation.w],
+            linear_velocity: [linear_velocity.x, linear_velocity.y, linear_velocity.z],
+            flags,
+            angular_velocity: [angular_velocity.x, angular_velocity.y, angular_velocity.z],
+            _padding: 0,
+        }
+    }
+}
+
+/// Creates a storage buffer for the joints of both hands, written with `HandJoints::write_buffer`.
+pub fn create_hand_joints_buffer(device: &Device) -> Buffer {
+    device.create_buffer(&BufferDescriptor {
+        label: Some("hand joints"),
+        size: HAND_JOINTS_BUFFER_SIZE,
+        usage: BufferUsages::STORAGE | BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
@@ -0,0 +1,264 @@
+use openxr::sys;
+
+use crate::{
+    ActionManifest, ActionManifestEntry, ActionSetManifest, ActionType, Actions, Error, cvt,
+};
+
+const HAPTICS_ACTION_SET: &str = "indite_haptics";
+const VIBRATE_ACTION: &str = "vibrate";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+impl Hand {
+    /// Top level user path of the hand.
+    pub fn path(self) -> &'static str {
+        match self {
+            Hand::Left => "/user/hand/left",
+            Hand::Right => "/user/hand/right",
+        }
+    }
+}
+
+/// Interaction profiles from the core specification with haptic outputs on both hands.
+pub const HAPTIC_INTERACTION_PROFILES: &[&str] = &[
+    "/interaction_profiles/khr/simple_controller",
+    "/interaction_profiles/htc/vive_controller",
+    "/interaction_profiles/microsoft/motion_controller",
+    "/interaction_profiles/oculus/touch_controller",
+    "/interaction_profiles/valve/index_controller",
+];
+
+/// Adds the haptic output actions used by `Haptics` to a manifest.
+///
+/// The actions are bound to the haptic output of both hands, for the interaction profiles in the
+/// manifest that are also in `profiles`, like `HAPTIC_INTERACTION_PROFILES`. Profiles without
+/// haptic outputs must be left out, as the runtime rejects their bindings. Call this before
+/// `create_actions`, as action sets can't be added after. Calling it again has no effect.
+pub fn add_haptic_actions(manifest: &mut ActionManifest, profiles: &[&str]) {
+    let hands = [Hand::Left, Hand::Right];
+
+    let has_set = manifest
+        .action_sets
+        .iter()
+        .any(|set| set.name == HAPTICS_ACTION_SET);
+    if !has_set {
+        manifest.action_sets.push(ActionSetManifest {
+            name: HAPTICS_ACTION_SET.to_string(),
+            localized_name: "Haptics".to_string(),
+            priority: 0,
+            actions: vec![ActionManifestEntry {
+                name: VIBRATE_ACTION.to_string(),
+                localized_name: "Vibrate".to_string(),
+                ty: ActionType::Haptic,
+                subaction_paths: hands.iter().map(|hand| hand.path().to_string()).collect(),
+            }],
+        });
+    }
+
+    let name = format!("{}/{}", HAPTICS_ACTION_SET, VIBRATE_ACTION);
+    let paths: Vec<_> = hands
+        .iter()
+        .map(|hand| format!("{}/output/haptic", hand.path()))
+        .collect();
+    for profile in &mut manifest.interaction_profiles {
+        if profiles.contains(&profile.profile.as_str()) {
+            profile.bindings.insert(name.clone(), paths.clone());
+        }
+    }
+}
+
+/// Haptic feedback on the controllers.
+///
+/// Requires the actions from `add_haptic_actions` to be part of the manifest `Actions` were created
+/// from.
+pub struct Haptics {
+    xr_session: openxr::Session<openxr::Vulkan>,
+    action: openxr::Action<openxr::Haptic>,
+    hand_paths: [openxr::Path; 2],
+    supports_pcm: bool,
+}
+
+impl Haptics {
+    pub fn new(
+        xr_session: &openxr::Session<openxr::Vulkan>,
+        actions: &Actions,
+    ) -> Result<Self, Error> {
+        let xr_instance = xr_session.instance();
+        let action = actions
+            .haptic_action(&format!("{}/{}", HAPTICS_ACTION_SET, VIBRATE_ACTION))
+            .map_err(|_| Error::MissingHapticActions)?
+            .clone();
+        let hand_paths = [
+            xr_instance.string_to_path(Hand::Left.path())?,
+            xr_instance.string_to_path(Hand::Right.path())?,
+        ];
+
+        Ok(Self {
+            xr_session: xr_session.clone(),
+            action,
+            hand_paths,
+            supports_pcm: xr_instance.exts().fb_haptic_pcm.is_some(),
+        })
+    }
+
+    /// If `XR_FB_haptic_pcm` is enabled, and PCM samples can be played.
+    ///
+    /// When it isn't, `vibrate_pcm` does nothing.
+    pub fn supports_pcm(&self) -> bool {
+        self.supports_pcm
+    }
+
+    /// Vibrates the controller in `hand`, replacing any ongoing vibration.
+    ///
+    /// `amplitude` is from 0.0 to 1.0. A `frequency` of 0.0 lets the runtime pick an optimal
+    /// frequency, and a `duration` of `openxr::Duration::MIN_HAPTIC` plays a minimal short pulse.
+    pub fn vibrate(
+        &self,
+        hand: Hand,
+        amplitude: f32,
+        frequency: f32,
+        duration: openxr::Duration,
+    ) -> Result<(), Error> {
+        let vibration = openxr::HapticVibration::new()
+            .amplitude(amplitude)
+            .frequency(frequency)
+            .duration(duration);
+        self.action
+            .apply_feedback(&self.xr_session, self.hand_path(hand), &vibration)?;
+
+        Ok(())
+    }
+
+    /// Stops any ongoing vibration of the controller in `hand`.
+    pub fn stop(&self, hand: Hand) -> Result<(), Error> {
+        self.action
+            .stop_feedback(&self.xr_session, self.hand_path(hand))?;
+
+        Ok(())
+    }
+
+    /// Sample rate the controller in `hand` plays PCM samples at, if PCM is supported.
+    pub fn pcm_sample_rate(&self, hand: Hand) -> Result<Option<f32>, Error> {
+        let Some(fb_haptic_pcm) = self.xr_session.instance().exts().fb_haptic_pcm.as_ref() else {
+            return Ok(None);
+        };
+
+        let info = self.action_info(hand);
+        let mut state = sys::DevicePcmSampleRateStateFB {
+            ty: sys::DevicePcmSampleRateStateFB::TYPE,
+            next: std::ptr::null_mut(),
+            sample_rate: 0.0,
+        };
+        unsafe {
+            cvt((fb_haptic_pcm.get_device_sample_rate)(
+                self.xr_session.as_raw(),
+                &info,
+                &mut state,
+            ))?;
+        }
+
+        Ok(Some(state.sample_rate))
+    }
+
+    /// Plays PCM samples on the controller in `hand`, with amplitudes from -1.0 to 1.0.
+    ///
+    /// If `append` is true the samples are queued after the ones already playing, otherwise they
+    /// replace them. The runtime may not consume all samples at once, the number consumed is
+    /// returned so the rest can be submitted later. Does nothing and returns 0 if PCM isn't
+    /// supported.
+    pub fn vibrate_pcm(
+        &self,
+        hand: Hand,
+        samples: &[f32],
+        sample_rate: f32,
+        append: bool,
+    ) -> Result<u32, Error> {
+        if !self.supports_pcm {
+            return Ok(0);
+        }
+
+        let info = self.action_info(hand);
+        let mut samples_consumed = 0;
+        let vibration = sys::HapticPcmVibrationFB {
+            ty: sys::HapticPcmVibrationFB::TYPE,
+            next: std::ptr::null(),
+            buffer_size: samples.len() as u32,
+            buffer: samples.as_ptr(),
+            sample_rate,
+            append: append.into(),
+            samples_consumed: &mut samples_consumed,
+        };
+
+        // There's no wrapper for PCM vibrations, so go through the core function directly
+        unsafe {
+            cvt((self.xr_session.instance().fp().apply_haptic_feedback)(
+                self.xr_session.as_raw(),
+                &info,
+                &vibration as *const _ as *const sys::HapticBaseHeader,
+            ))?;
+        }
+
+        Ok(samples_consumed)
+    }
+
+    fn hand_path(&self, hand: Hand) -> openxr::Path {
+        match hand {
+            Hand::Left => self.hand_paths[0],
+            Hand::Right => self.hand_paths[1],
+        }
+    }
+
+    fn action_info(&self, hand: Hand) -> sys::HapticActionInfo {
+        sys::HapticActionInfo {
+            ty: sys::HapticActionInfo::TYPE,
+            next: std::ptr::null(),
+            action: self.action.as_raw(),
+            subaction_path: self.hand_path(hand),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::InteractionProfileManifest;
+
+    use super::*;
+
+    fn profile(profile: &str) -> InteractionProfileManifest {
+        InteractionProfileManifest {
+            profile: profile.to_string(),
+            bindings: Default::default(),
+        }
+    }
+
+    #[test]
+    fn binds_only_haptic_profiles() {
+        let mut manifest = ActionManifest {
+            action_sets: Vec::new(),
+            interaction_profiles: vec![
+                profile("/interaction_profiles/oculus/touch_controller"),
+                profile("/interaction_profiles/ext/hand_interaction_ext"),
+            ],
+        };
+
+        add_haptic_actions(&mut manifest, HAPTIC_INTERACTION_PROFILES);
+        add_haptic_actions(&mut manifest, HAPTIC_INTERACTION_PROFILES);
+
+        assert_eq!(manifest.action_sets.len(), 1);
+        let [touch, hand] = &manifest.interaction_profiles[..] else {
+            panic!("profiles were added or removed");
+        };
+        assert_eq!(
+            touch.bindings["indite_haptics/vibrate"],
+            [
+                "/user/hand/left/output/haptic",
+                "/user/hand/right/output/haptic"
+            ]
+        );
+        assert!(hand.bindings.is_empty());
+    }
+}
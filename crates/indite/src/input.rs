@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+/// State of every action, captured once per frame by `Actions::snapshot`.
+///
+/// Actions are looked up by `set/action` name, and subaction path. Use `Path::NULL` to get the
+/// state combined over all subaction paths.
+#[derive(Debug, Clone)]
+pub struct InputSnapshot {
+    time: openxr::Time,
+    states: HashMap<String, Vec<(openxr::Path, ActionValue)>>,
+}
+
+impl InputSnapshot {
+    pub(crate) fn new(
+        time: openxr::Time,
+        states: HashMap<String, Vec<(openxr::Path, ActionValue)>>,
+    ) -> Self {
+        Self { time, states }
+    }
+
+    /// Time poses were located at.
+    pub fn time(&self) -> openxr::Time {
+        self.time
+    }
+
+    /// Gets the captured state of an action, `None` if there's no such action or subaction path.
+    pub fn get(&self, name: &str, subaction_path: openxr::Path) -> Option<&ActionValue> {
+        self.states
+            .get(name)?
+            .iter()
+            .find(|(path, _)| *path == subaction_path)
+            .map(|(_, value)| value)
+    }
+
+    pub fn bool(
+        &self,
+        name: &str,
+        subaction_path: openxr::Path,
+    ) -> Option<openxr::ActionState<bool>> {
+        match self.get(name, subaction_path)? {
+            ActionValue::Bool(state) => Some(*state),
+            _ => None,
+        }
+    }
+
+    pub fn float(
+        &self,
+        name: &str,
+        subaction_path: openxr::Path,
+    ) -> Option<openxr::ActionState<f32>> {
+        match self.get(name, subaction_path)? {
+            ActionValue::Float(state) => Some(*state),
+            _ => None,
+        }
+    }
+
+    pub fn vector2f(
+        &self,
+        name: &str,
+        subaction_path: openxr::Path,
+    ) -> Option<openxr::ActionState<openxr::Vector2f>> {
+        match self.get(name, subaction_path)? {
+            ActionValue::Vector2f(state) => Some(*state),
+            _ => None,
+        }
+    }
+
+    pub fn pose(&self, name: &str, subaction_path: openxr::Path) -> Option<PoseState> {
+        match self.get(name, subaction_path)? {
+            ActionValue::Pose(state) => Some(*state),
+            _ => None,
+        }
+    }
+}
+
+/// Captured state of a single action.
+#[derive(Debug, Clone, Copy)]
+pub enum ActionValue {
+    Bool(openxr::ActionState<bool>),
+    Float(openxr::ActionState<f32>),
+    Vector2f(openxr::ActionState<openxr::Vector2f>),
+    Pose(PoseState),
+}
+
+/// Captured state of a pose action.
+#[derive(Debug, Clone, Copy)]
+pub struct PoseState {
+    /// If the action is bound to an input that's currently available.
+    pub is_active: bool,
+    pub location: TrackedPose,
+}
+
+/// Pose located in a space, with its location flags decoded.
+#[derive(Debug, Clone, Copy)]
+pub struct TrackedPose {
+    pub pose: openxr::Posef,
+    /// If `pose.position` can be used. It may still be estimated rather than tracked.
+    pub position_valid: bool,
+    /// If `pose.orientation` can be used. It may still be estimated rather than tracked.
+    pub orientation_valid: bool,
+    /// If the position is actively tracked, rather than estimated.
+    pub position_tracked: bool,
+    /// If the orientation is actively tracked, rather than estimated.
+    pub orientation_tracked: bool,
+}
+
+impl TrackedPose {
+    pub(crate) fn from_xr(location: openxr::SpaceLocation) -> Self {
+        Self::new(location.pose, location.location_flags)
+    }
+
+    pub(crate) fn new(pose: openxr::Posef, flags: openxr::SpaceLocationFlags) -> Self {
+        Self {
+            pose,
+            position_valid: flags.contains(openxr::SpaceLocationFlags::POSITION_VALID),
+            orientation_valid: flags.contains(openxr::SpaceLocationFlags::ORIENTATION_VALID),
+            position_tracked: flags.contains(openxr::SpaceLocationFlags::POSITION_TRACKED),
+            orientation_tracked: flags.contains(openxr::SpaceLocationFlags::ORIENTATION_TRACKED),
+        }
+    }
+
+    /// If both position and orientation are valid.
+    pub fn is_valid(&self) -> bool {
+        self.position_valid && self.orientation_valid
+    }
+}
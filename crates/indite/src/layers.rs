@@ -0,0 +1,548 @@
+use std::{f32::consts::PI, marker::PhantomData, ptr};
+
+use openxr::{CompositionLayerFlags, CompositionLayerProjectionView, sys};
+
+use crate::{Error, Swapchain, swapchain::SwapchainKind};
+
+/// How views are laid out in a swapchain's images.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ViewLayout {
+    /// Every view is in its own array layer, like multiview rendering does.
+    #[default]
+    ArrayLayers,
+    /// Views are next to each other in the first array layer, each taking an equal part of the
+    /// width.
+    SideBySide,
+}
+
+/// Builds a projection layer from located views.
+pub struct ProjectionLayerBuilder<'a> {
+    swapchain: &'a Swapchain,
+    views: &'a [openxr::View],
+    space: &'a openxr::Space,
+    layout: ViewLayout,
+    sub_rects: Option<&'a [openxr::Rect2Di]>,
+    layer_flags: CompositionLayerFlags,
+    alpha_blend: bool,
+    unpremultiplied_alpha: bool,
+    environment_blend_mode: Option<openxr::EnvironmentBlendMode>,
+}
+
+impl<'a> ProjectionLayerBuilder<'a> {
+    /// `views` should be located in `space`, at the display time of the frame the layer is
+    /// submitted in.
+    pub fn new(
+        swapchain: &'a Swapchain,
+        views: &'a [openxr::View],
+        space: &'a openxr::Space,
+    ) -> Self {
+        Self {
+            swapchain,
+            views,
+            space,
+            layout: ViewLayout::default(),
+            sub_rects: None,
+            layer_flags: CompositionLayerFlags::EMPTY,
+            alpha_blend: false,
+            unpremultiplied_alpha: false,
+            environment_blend_mode: None,
+        }
+    }
+
+    pub fn layout(mut self, layout: ViewLayout) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Overrides the rect in the swapchain images of every view, one rect per view.
+    ///
+    /// The array layer views are in is still decided by the layout.
+    pub fn sub_rects(mut self, sub_rects: &'a [openxr::Rect2Di]) -> Self {
+        self.sub_rects = Some(sub_rects);
+        self
+    }
+
+    pub fn layer_flags(mut self, layer_flags: CompositionLayerFlags) -> Self {
+        self.layer_flags = layer_flags;
+        self
+    }
+
+    /// Blends the layer with the layers behind it using the alpha channel, like passthrough.
+    ///
+    /// Alpha is expected to be premultiplied, see `unpremultiplied_alpha`.
+    pub fn alpha_blend(mut self, alpha_blend: bool) -> Self {
+        self.alpha_blend = alpha_blend;
+        self
+    }
+
+    /// Tells the runtime the rendered colors aren't premultiplied by alpha.
+    ///
+    /// Only has effect when the layer is alpha blended.
+    pub fn unpremultiplied_alpha(mut self, unpremultiplied_alpha: bool) -> Self {
+        self.unpremultiplied_alpha = unpremultiplied_alpha;
+        self
+    }
+
+    /// Blend mode the frame is submitted with, so layer flags can be set to match it.
+    ///
+    /// With `ALPHA_BLEND`, the layer is alpha blended with the environment behind it.
+    pub fn environment_blend_mode(mut self, mode: openxr::EnvironmentBlendMode) -> Self {
+        self.environment_blend_mode = Some(mode);
+        self
+    }
+
+    pub fn build(self) -> Result<ProjectionLayer<'a>, Error> {
+        let desc = self.swapchain.desc();
+        let view_count = self.views.len() as u32;
+
+        if self.swapchain.kind() != SwapchainKind::Color {
+            return Err(Error::NotColorSwapchain);
+        }
+        if view_count == 0 {
+            return Err(Error::NoViews);
+        }
+        if let Some(sub_rects) = self.sub_rects
+            && sub_rects.len() != self.views.len()
+        {
+            return Err(Error::SubRectCountMismatch {
+                sub_rects: sub_rects.len(),
+                views: self.views.len(),
+            });
+        }
+        if self.layout == ViewLayout::ArrayLayers && view_count > desc.view_count {
+            return Err(Error::TooManyViews {
+                views: view_count,
+                layers: desc.view_count,
+            });
+        }
+
+        let views = self
+            .views
+            .iter()
+            .enumerate()
+            .map(|(index, view)| {
+                let index = index as u32;
+
+                let (image_array_index, default_rect) = match self.layout {
+                    ViewLayout::ArrayLayers => (index, rect(0, desc.width, desc.height)),
+                    ViewLayout::SideBySide => {
+                        let width = desc.width / view_count;
+                        (0, rect(index * width, width, desc.height))
+                    }
+                };
+                let image_rect = self
+                    .sub_rects
+                    .map(|sub_rects| sub_rects[index as usize])
+                    .unwrap_or(default_rect);
+
+                CompositionLayerProjectionView::new()
+                    .pose(view.pose)
+                    .fov(view.fov)
+                    .sub_image(sub_image(self.swapchain, image_rect, image_array_index))
+            })
+            .collect();
+
+        let alpha_blend = self.alpha_blend
+            || self.environment_blend_mode == Some(openxr::EnvironmentBlendMode::ALPHA_BLEND);
+
+        let mut layer_flags = self.layer_flags;
+        if alpha_blend {
+            layer_flags |= CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA;
+
+            if self.unpremultiplied_alpha {
+                layer_flags |= CompositionLayerFlags::UNPREMULTIPLIED_ALPHA;
+            }
+        }
+
+        Ok(ProjectionLayer {
+            space: self.space,
+            layer_flags,
+            views,
+        })
+    }
+}
+
+/// Projection layer created by `ProjectionLayerBuilder`.
+pub struct ProjectionLayer<'a> {
+    space: &'a openxr::Space,
+    layer_flags: CompositionLayerFlags,
+    views: Vec<CompositionLayerProjectionView<'a, openxr::Vulkan>>,
+}
+
+impl<'a> ProjectionLayer<'a> {
+    /// Views of the layer, for attaching additional information like depth.
+    pub fn views_mut(&mut self) -> &mut [CompositionLayerProjectionView<'a, openxr::Vulkan>] {
+        &mut self.views
+    }
+
+    /// Gets the layer to submit at the end of the frame.
+    pub fn layer(&self) -> openxr::CompositionLayerProjection<'_, openxr::Vulkan> {
+        openxr::CompositionLayerProjection::new()
+            .layer_flags(self.layer_flags)
+            .space(self.space)
+            .views(&self.views)
+    }
+}
+
+fn rect(x: u32, width: u32, height: u32) -> openxr::Rect2Di {
+    openxr::Rect2Di {
+        offset: openxr::Offset2Di { x: x as _, y: 0 },
+        extent: openxr::Extent2Di {
+            width: width as _,
+            height: height as _,
+        },
+    }
+}
+
+fn sub_image(
+    swapchain: &Swapchain,
+    image_rect: openxr::Rect2Di,
+    image_array_index: u32,
+) -> openxr::SwapchainSubImage<'_, openxr::Vulkan> {
+    let raw = sys::SwapchainSubImage {
+        swapchain: swapchain.as_raw(),
+        image_rect,
+        image_array_index,
+    };
+
+    // The swapchain handle is valid for as long as the swapchain is borrowed
+    unsafe { openxr::SwapchainSubImage::from_raw(raw) }
+}
+
+/// Implements setters for the `LayerCommon` fields a layer builder exposes.
+macro_rules! common_setters {
+    ($builder:ident: $($setter:ident),* $(,)?) => {
+        impl $builder<'_> {
+            $(common_setters!(@setter $setter);)*
+        }
+    };
+    (@setter pose) => {
+        /// Pose of the layer, the center for cylinder and equirect layers.
+        pub fn pose(mut self, pose: openxr::Posef) -> Self {
+            self.common.pose = pose;
+            self
+        }
+    };
+    (@setter eye_visibility) => {
+        pub fn eye_visibility(mut self, eye_visibility: openxr::EyeVisibility) -> Self {
+            self.common.eye_visibility = eye_visibility;
+            self
+        }
+    };
+    (@setter sub_rect) => {
+        /// Rect in the swapchain images to show, the full image by default.
+        pub fn sub_rect(mut self, sub_rect: openxr::Rect2Di) -> Self {
+            self.common.sub_rect = Some(sub_rect);
+            self
+        }
+    };
+    (@setter image_array_index) => {
+        pub fn image_array_index(mut self, image_array_index: u32) -> Self {
+            self.common.image_array_index = image_array_index;
+            self
+        }
+    };
+    (@setter layer_flags) => {
+        pub fn layer_flags(mut self, layer_flags: CompositionLayerFlags) -> Self {
+            self.common.layer_flags = layer_flags;
+            self
+        }
+    };
+}
+
+/// Builds a quad layer, a flat rectangle placed in the world.
+pub struct QuadLayerBuilder<'a> {
+    common: LayerCommon<'a>,
+    size: openxr::Extent2Df,
+}
+
+impl<'a> QuadLayerBuilder<'a> {
+    /// `size` is the size of the quad in meters.
+    pub fn new(
+        swapchain: &'a Swapchain,
+        space: &'a openxr::Space,
+        size: openxr::Extent2Df,
+    ) -> Self {
+        Self {
+            common: LayerCommon::new(swapchain, space),
+            size,
+        }
+    }
+
+    pub fn build(self) -> Result<CompositionLayer<'a, sys::CompositionLayerQuad>, Error> {
+        let common = &self.common;
+        let raw = sys::CompositionLayerQuad {
+            ty: sys::CompositionLayerQuad::TYPE,
+            next: ptr::null(),
+            layer_flags: common.layer_flags,
+            space: common.space.as_raw(),
+            eye_visibility: common.eye_visibility,
+            sub_image: common.sub_image()?,
+            pose: common.pose,
+            size: self.size,
+        };
+
+        Ok(CompositionLayer::new(raw))
+    }
+}
+
+common_setters!(QuadLayerBuilder: pose, eye_visibility, sub_rect, image_array_index, layer_flags);
+
+/// Builds a cylinder layer, a curved surface around the viewer.
+///
+/// Requires `XR_KHR_composition_layer_cylinder` to be enabled on the instance.
+pub struct CylinderLayerBuilder<'a> {
+    common: LayerCommon<'a>,
+    radius: f32,
+    central_angle: f32,
+    aspect_ratio: f32,
+}
+
+impl<'a> CylinderLayerBuilder<'a> {
+    /// `central_angle` is the visible horizontal arc of the cylinder in radians, and
+    /// `aspect_ratio` the width of the visible part divided by its height.
+    pub fn new(
+        swapchain: &'a Swapchain,
+        space: &'a openxr::Space,
+        radius: f32,
+        central_angle: f32,
+        aspect_ratio: f32,
+    ) -> Self {
+        Self {
+            common: LayerCommon::new(swapchain, space),
+            radius,
+            central_angle,
+            aspect_ratio,
+        }
+    }
+
+    /// Fails if `XR_KHR_composition_layer_cylinder` isn't enabled on the instance.
+    pub fn build(
+        self,
+        xr_instance: &openxr::Instance,
+    ) -> Result<CompositionLayer<'a, sys::CompositionLayerCylinderKHR>, Error> {
+        if xr_instance.exts().khr_composition_layer_cylinder.is_none() {
+            return Err(Error::missing_extension(
+                "XR_KHR_composition_layer_cylinder",
+            ));
+        }
+
+        let common = &self.common;
+        let raw = sys::CompositionLayerCylinderKHR {
+            ty: sys::CompositionLayerCylinderKHR::TYPE,
+            next: ptr::null(),
+            layer_flags: common.layer_flags,
+            space: common.space.as_raw(),
+            eye_visibility: common.eye_visibility,
+            sub_image: common.sub_image()?,
+            pose: common.pose,
+            radius: self.radius,
+            central_angle: self.central_angle,
+            aspect_ratio: self.aspect_ratio,
+        };
+
+        Ok(CompositionLayer::new(raw))
+    }
+}
+
+common_setters!(CylinderLayerBuilder: pose, eye_visibility, sub_rect, image_array_index, layer_flags);
+
+/// Builds an equirect layer, an equirectangular image mapped onto a sphere around the viewer.
+///
+/// Requires `XR_KHR_composition_layer_equirect2` to be enabled on the instance.
+pub struct EquirectLayerBuilder<'a> {
+    common: LayerCommon<'a>,
+    radius: f32,
+    central_horizontal_angle: f32,
+    upper_vertical_angle: f32,
+    lower_vertical_angle: f32,
+}
+
+impl<'a> EquirectLayerBuilder<'a> {
+    /// Creates a builder for a full sphere, with an infinite radius.
+    pub fn new(swapchain: &'a Swapchain, space: &'a openxr::Space) -> Self {
+        Self {
+            common: LayerCommon::new(swapchain, space),
+            // Zero is treated as an infinite sphere by the runtime
+            radius: 0.0,
+            central_horizontal_angle: 2.0 * PI,
+            upper_vertical_angle: 0.5 * PI,
+            lower_vertical_angle: -0.5 * PI,
+        }
+    }
+
+    /// Radius of the sphere in meters, zero for an infinite sphere.
+    pub fn radius(mut self, radius: f32) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    /// Visible part of the sphere, in radians.
+    pub fn angles(
+        mut self,
+        central_horizontal_angle: f32,
+        upper_vertical_angle: f32,
+        lower_vertical_angle: f32,
+    ) -> Self {
+        self.central_horizontal_angle = central_horizontal_angle;
+        self.upper_vertical_angle = upper_vertical_angle;
+        self.lower_vertical_angle = lower_vertical_angle;
+        self
+    }
+
+    /// Fails if `XR_KHR_composition_layer_equirect2` isn't enabled on the instance.
+    pub fn build(
+        self,
+        xr_instance: &openxr::Instance,
+    ) -> Result<CompositionLayer<'a, sys::CompositionLayerEquirect2KHR>, Error> {
+        if xr_instance.exts().khr_composition_layer_equirect2.is_none() {
+            return Err(Error::missing_extension(
+                "XR_KHR_composition_layer_equirect2",
+            ));
+        }
+
+        let common = &self.common;
+        let raw = sys::CompositionLayerEquirect2KHR {
+            ty: sys::CompositionLayerEquirect2KHR::TYPE,
+            next: ptr::null(),
+            layer_flags: common.layer_flags,
+            space: common.space.as_raw(),
+            eye_visibility: common.eye_visibility,
+            sub_image: common.sub_image()?,
+            pose: common.pose,
+            radius: self.radius,
+            central_horizontal_angle: self.central_horizontal_angle,
+            upper_vertical_angle: self.upper_vertical_angle,
+            lower_vertical_angle: self.lower_vertical_angle,
+        };
+
+        Ok(CompositionLayer::new(raw))
+    }
+}
+
+common_setters!(EquirectLayerBuilder: pose, eye_visibility, sub_rect, image_array_index, layer_flags);
+
+/// Builds a cube layer, a cube map surrounding the viewer at infinite distance, like a skybox.
+///
+/// The swapchain must be created with `create_cube_swapchain`. Requires
+/// `XR_KHR_composition_layer_cube` to be enabled on the instance.
+pub struct CubeLayerBuilder<'a> {
+    common: LayerCommon<'a>,
+}
+
+impl<'a> CubeLayerBuilder<'a> {
+    pub fn new(swapchain: &'a Swapchain, space: &'a openxr::Space) -> Self {
+        Self {
+            common: LayerCommon::new(swapchain, space),
+        }
+    }
+
+    /// Orientation of the cube map, only the rotation of the pose is used.
+    pub fn orientation(mut self, orientation: openxr::Quaternionf) -> Self {
+        self.common.pose.orientation = orientation;
+        self
+    }
+
+    /// Fails if `XR_KHR_composition_layer_cube` isn't enabled on the instance.
+    pub fn build(
+        self,
+        xr_instance: &openxr::Instance,
+    ) -> Result<CompositionLayer<'a, sys::CompositionLayerCubeKHR>, Error> {
+        if xr_instance.exts().khr_composition_layer_cube.is_none() {
+            return Err(Error::missing_extension("XR_KHR_composition_layer_cube"));
+        }
+        if self.common.swapchain.kind() != SwapchainKind::Cube {
+            return Err(Error::NotCubeSwapchain);
+        }
+
+        let common = &self.common;
+        let raw = sys::CompositionLayerCubeKHR {
+            ty: sys::CompositionLayerCubeKHR::TYPE,
+            next: ptr::null(),
+            layer_flags: common.layer_flags,
+            space: common.space.as_raw(),
+            eye_visibility: common.eye_visibility,
+            swapchain: common.swapchain.as_raw(),
+            image_array_index: 0,
+            orientation: common.pose.orientation,
+        };
+
+        Ok(CompositionLayer::new(raw))
+    }
+}
+
+common_setters!(CubeLayerBuilder: eye_visibility, layer_flags);
+
+/// Layer created by one of the layer builders, borrowing the swapchain and space it was built
+/// from.
+pub struct CompositionLayer<'a, T> {
+    raw: T,
+    _marker: PhantomData<&'a Swapchain>,
+}
+
+impl<T> CompositionLayer<'_, T> {
+    pub(crate) fn new(raw: T) -> Self {
+        Self {
+            raw,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn as_raw(&self) -> &T {
+        &self.raw
+    }
+
+    /// Gets the layer to submit at the end of the frame.
+    pub fn layer(&self) -> &openxr::CompositionLayerBase<'_, openxr::Vulkan> {
+        // Every layer struct starts with the base header, and `CompositionLayerBase` is a
+        // transparent wrapper around it. This is how openxr's own layer types convert as well.
+        unsafe { &*(&self.raw as *const T as *const openxr::CompositionLayerBase<openxr::Vulkan>) }
+    }
+}
+
+/// Fields shared by the builders of single image layers.
+struct LayerCommon<'a> {
+    swapchain: &'a Swapchain,
+    space: &'a openxr::Space,
+    pose: openxr::Posef,
+    eye_visibility: openxr::EyeVisibility,
+    sub_rect: Option<openxr::Rect2Di>,
+    image_array_index: u32,
+    layer_flags: CompositionLayerFlags,
+}
+
+impl<'a> LayerCommon<'a> {
+    fn new(swapchain: &'a Swapchain, space: &'a openxr::Space) -> Self {
+        Self {
+            swapchain,
+            space,
+            pose: openxr::Posef::IDENTITY,
+            eye_visibility: openxr::EyeVisibility::BOTH,
+            sub_rect: None,
+            image_array_index: 0,
+            layer_flags: CompositionLayerFlags::EMPTY,
+        }
+    }
+
+    /// Sub image for a layer showing colors, which can't use depth or cube swapchains.
+    fn sub_image(&self) -> Result<sys::SwapchainSubImage, Error> {
+        let desc = self.swapchain.desc();
+        if self.swapchain.kind() != SwapchainKind::Color {
+            return Err(Error::NotColorSwapchain);
+        }
+        if self.image_array_index >= desc.view_count {
+            return Err(Error::ImageArrayIndexOutOfRange {
+                index: self.image_array_index,
+                layers: desc.view_count,
+            });
+        }
+
+        Ok(sys::SwapchainSubImage {
+            swapchain: self.swapchain.as_raw(),
+            image_rect: self
+                .sub_rect
+                .unwrap_or_else(|| rect(0, desc.width, desc.height)),
+            image_array_index: self.image_array_index,
+        })
+    }
+}
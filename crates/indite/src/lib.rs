@@ -1,15 +1,68 @@
+mod actions;
 mod context;
 mod debug_utils;
+mod depth;
+mod error;
+mod format;
+mod foveation;
+mod frame;
+#[cfg(feature = "gles")]
+pub mod gles;
+mod hand_tracking;
+mod haptics;
+mod input;
+mod layers;
+mod passthrough;
+mod performance;
+mod refresh_rate;
+mod session;
+mod spaces;
 mod swapchain;
+mod visibility_mask;
+mod vulkan_enable;
 
-use anyhow::{Context, Error};
 use ash::vk::Handle;
 use wgpu::{Device, Instance, hal::api::Vulkan};
 
 pub use self::{
-    context::{create_device, create_instance},
+    actions::{
+        ActionManifest, ActionManifestEntry, ActionSetManifest, ActionType, Actions, AnyAction,
+        InteractionProfileManifest, create_actions,
+    },
+    context::{
+        DeviceConfig, DeviceCreateHook, InstanceBuilder, InstanceInfo, create_device,
+        create_instance,
+    },
     debug_utils::DebugUtils,
-    swapchain::{SwapchainDescriptor, SwapchainHandle, create_swapchain},
+    depth::{DepthInfoDescriptor, attach_depth_infos, create_depth_infos},
+    error::Error,
+    format::{vk_format_to_wgpu, wgpu_format_to_vk},
+    foveation::{FoveationDescriptor, FoveationLevel, FragmentDensityMap},
+    frame::{Frame, FrameLoop, select_environment_blend_mode},
+    hand_tracking::{
+        GpuHandJoint, HAND_JOINT_COUNT, HAND_JOINTS_BUFFER_SIZE, HandJoint, HandJoints,
+        HandTracking, create_hand_joints_buffer,
+    },
+    haptics::{HAPTIC_INTERACTION_PROFILES, Hand, Haptics, add_haptic_actions},
+    input::{ActionValue, InputSnapshot, PoseState, TrackedPose},
+    layers::{
+        CompositionLayer, CubeLayerBuilder, CylinderLayerBuilder, EquirectLayerBuilder,
+        ProjectionLayer, ProjectionLayerBuilder, QuadLayerBuilder, ViewLayout,
+    },
+    passthrough::{Passthrough, PassthroughColorMap, PassthroughStyle},
+    performance::{
+        PerfDomain, PerfLevel, PerfNotificationLevel, PerfSubDomain, PerformanceSettings,
+    },
+    refresh_rate::{DisplayRefreshRate, refresh_rate_from_period},
+    session::{SessionDriver, SessionEvent, SessionEvents, SessionState},
+    spaces::{ReferenceSpaceKind, TrackingSpace},
+    swapchain::{
+        ImageAcquired, ImageState, ImageWait, ImageWaited, Swapchain, SwapchainDescriptor,
+        SwapchainHandle, SwapchainImage, create_cube_swapchain, create_depth_swapchain,
+        create_swapchain,
+    },
+    visibility_mask::{VisibilityMask, VisibilityMaskType, VisibilityMaskVertex},
+    vulkan_enable::VulkanEnable,
 };
 
 pub fn create_session(
@@ -25,17 +78,9 @@ pub fn create_session(
     ),
     Error,
 > {
-    let hal_instance = unsafe {
-        instance
-            .as_hal::<Vulkan>()
-            .context("wgpu instance backend not vulkan")?
-    };
+    let hal_instance = unsafe { instance.as_hal::<Vulkan>().ok_or(Error::NotVulkan)? };
     let vk_instance = hal_instance.shared_instance().raw_instance();
-    let hal_device = unsafe {
-        device
-            .as_hal::<Vulkan>()
-            .context("wgpu device backend not vulkan")?
-    };
+    let hal_device = unsafe { device.as_hal::<Vulkan>().ok_or(Error::NotVulkan)? };
 
     let create_info = openxr::vulkan::SessionCreateInfo {
         instance: vk_instance.handle().as_raw() as _,
@@ -54,3 +99,12 @@ pub fn create_session(
 
     Ok((xr_session, xr_frame_wait, xr_frame_stream))
 }
+
+/// Checks the result of calling an OpenXR function directly, where openxr has no suitable wrapper.
+fn cvt(result: openxr::sys::Result) -> Result<openxr::sys::Result, openxr::sys::Result> {
+    if result.into_raw() >= 0 {
+        Ok(result)
+    } else {
+        Err(result)
+    }
+}
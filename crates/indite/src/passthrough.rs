@@ -0,0 +1,229 @@
+use openxr::sys::{self, Handle as _};
+
+use crate::{CompositionLayer, Error, cvt};
+
+/// Mixed reality passthrough of the camera feed, using `XR_FB_passthrough`.
+///
+/// Submit `layer` before the projection layer, so it's drawn behind it, and enable alpha blending
+/// on the projection layer so the feed shows through.
+pub struct Passthrough {
+    // Keeps the session alive for as long as the passthrough handles exist
+    _xr_session: openxr::Session<openxr::Vulkan>,
+    fp: openxr::raw::PassthroughFB,
+    passthrough: sys::PassthroughFB,
+    layer: sys::PassthroughLayerFB,
+    running: bool,
+}
+
+impl Passthrough {
+    /// Creates the passthrough feature and a layer showing the full camera feed, both started.
+    ///
+    /// Fails if `XR_FB_passthrough` isn't enabled on the instance.
+    pub fn new(xr_session: &openxr::Session<openxr::Vulkan>) -> Result<Self, Error> {
+        let fp = *xr_session
+            .instance()
+            .exts()
+            .fb_passthrough
+            .as_ref()
+            .ok_or_else(|| Error::missing_extension("XR_FB_passthrough"))?;
+
+        let passthrough_info = sys::PassthroughCreateInfoFB {
+            ty: sys::PassthroughCreateInfoFB::TYPE,
+            next: std::ptr::null(),
+            flags: sys::PassthroughFlagsFB::IS_RUNNING_AT_CREATION,
+        };
+        let mut passthrough = sys::PassthroughFB::NULL;
+        unsafe {
+            cvt((fp.create_passthrough)(
+                xr_session.as_raw(),
+                &passthrough_info,
+                &mut passthrough,
+            ))?;
+        }
+
+        let layer_info = sys::PassthroughLayerCreateInfoFB {
+            ty: sys::PassthroughLayerCreateInfoFB::TYPE,
+            next: std::ptr::null(),
+            passthrough,
+            flags: sys::PassthroughFlagsFB::IS_RUNNING_AT_CREATION,
+            purpose: sys::PassthroughLayerPurposeFB::RECONSTRUCTION,
+        };
+        let mut layer = sys::PassthroughLayerFB::NULL;
+        let result = unsafe {
+            cvt((fp.create_passthrough_layer)(
+                xr_session.as_raw(),
+                &layer_info,
+                &mut layer,
+            ))
+        };
+        if let Err(error) = result {
+            unsafe { (fp.destroy_passthrough)(passthrough) };
+            return Err(error.into());
+        }
+
+        Ok(Self {
+            _xr_session: xr_session.clone(),
+            fp,
+            passthrough,
+            layer,
+            running: true,
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Resumes the camera feed.
+    pub fn start(&mut self) -> Result<(), Error> {
+        if self.running {
+            return Ok(());
+        }
+
+        unsafe {
+            cvt((self.fp.passthrough_start)(self.passthrough))?;
+
+            // Undo the start, so the next call starts over instead of starting twice
+            if let Err(error) = cvt((self.fp.passthrough_layer_resume)(self.layer)) {
+                let _ = (self.fp.passthrough_pause)(self.passthrough);
+                return Err(error.into());
+            }
+        }
+        self.running = true;
+
+        Ok(())
+    }
+
+    /// Pauses the camera feed, the runtime can turn off the cameras while paused.
+    ///
+    /// The layer shouldn't be submitted while paused.
+    pub fn pause(&mut self) -> Result<(), Error> {
+        if !self.running {
+            return Ok(());
+        }
+
+        unsafe {
+            cvt((self.fp.passthrough_layer_pause)(self.layer))?;
+            cvt((self.fp.passthrough_pause)(self.passthrough))?;
+        }
+        self.running = false;
+
+        Ok(())
+    }
+
+    pub fn set_style(&self, style: &PassthroughStyle) -> Result<(), Error> {
+        // Only one of these gets chained, depending on the color map
+        let mono_to_rgba;
+        let mono_to_mono;
+        let brightness_contrast_saturation;
+
+        let next: *const std::ffi::c_void = match &style.color_map {
+            PassthroughColorMap::None => std::ptr::null(),
+            PassthroughColorMap::MonoToRgba(texture_color_map) => {
+                mono_to_rgba = sys::PassthroughColorMapMonoToRgbaFB {
+                    ty: sys::PassthroughColorMapMonoToRgbaFB::TYPE,
+                    next: std::ptr::null(),
+                    texture_color_map: **texture_color_map,
+                };
+                &mono_to_rgba as *const _ as *const _
+            }
+            PassthroughColorMap::MonoToMono(texture_color_map) => {
+                mono_to_mono = sys::PassthroughColorMapMonoToMonoFB {
+                    ty: sys::PassthroughColorMapMonoToMonoFB::TYPE,
+                    next: std::ptr::null(),
+                    texture_color_map: **texture_color_map,
+                };
+                &mono_to_mono as *const _ as *const _
+            }
+            PassthroughColorMap::BrightnessContrastSaturation {
+                brightness,
+                contrast,
+                saturation,
+            } => {
+                brightness_contrast_saturation = sys::PassthroughBrightnessContrastSaturationFB {
+                    ty: sys::PassthroughBrightnessContrastSaturationFB::TYPE,
+                    next: std::ptr::null(),
+                    brightness: *brightness,
+                    contrast: *contrast,
+                    saturation: *saturation,
+                };
+                &brightness_contrast_saturation as *const _ as *const _
+            }
+        };
+
+        let raw_style = sys::PassthroughStyleFB {
+            ty: sys::PassthroughStyleFB::TYPE,
+            next,
+            texture_opacity_factor: style.opacity,
+            edge_color: style.edge_color,
+        };
+        unsafe {
+            cvt((self.fp.passthrough_layer_set_style)(
+                self.layer, &raw_style,
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Gets the passthrough layer, to submit at the end of the frame.
+    pub fn layer(&self) -> CompositionLayer<'_, sys::CompositionLayerPassthroughFB> {
+        CompositionLayer::new(sys::CompositionLayerPassthroughFB {
+            ty: sys::CompositionLayerPassthroughFB::TYPE,
+            next: std::ptr::null(),
+            flags: openxr::CompositionLayerFlags::BLEND_TEXTURE_SOURCE_ALPHA,
+            // Passthrough layers aren't placed in a space
+            space: sys::Space::NULL,
+            layer_handle: self.layer,
+        })
+    }
+}
+
+impl Drop for Passthrough {
+    fn drop(&mut self) {
+        unsafe {
+            (self.fp.destroy_passthrough_layer)(self.layer);
+            (self.fp.destroy_passthrough)(self.passthrough);
+        }
+    }
+}
+
+/// Appearance of the passthrough camera feed.
+#[derive(Debug, Clone)]
+pub struct PassthroughStyle {
+    /// Opacity of the camera feed, from 0.0 to 1.0.
+    pub opacity: f32,
+    /// Color of edges detected in the camera feed, fully transparent to not draw them.
+    pub edge_color: openxr::Color4f,
+    pub color_map: PassthroughColorMap,
+}
+
+impl Default for PassthroughStyle {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            edge_color: openxr::Color4f {
+                r: 0.0,
+                g: 0.0,
+                b: 0.0,
+                a: 0.0,
+            },
+            color_map: PassthroughColorMap::None,
+        }
+    }
+}
+
+/// Mapping from the camera feed's brightness to output colors.
+#[derive(Debug, Clone)]
+pub enum PassthroughColorMap {
+    None,
+    MonoToRgba(Box<[openxr::Color4f; 256]>),
+    MonoToMono(Box<[u8; 256]>),
+    /// Adjustments, with brightness from -100.0 to 100.0, and contrast and saturation neutral at
+    /// 1.0.
+    BrightnessContrastSaturation {
+        brightness: f32,
+        contrast: f32,
+        saturation: f32,
+    },
+}
@@ -0,0 +1,136 @@
+use openxr::sys;
+
+use crate::{Error, cvt};
+
+/// Performance levels of the session, using `XR_EXT_performance_settings`.
+///
+/// Notifications about the runtime approaching its limits are reported as
+/// `SessionEvent::PerfSettingsChanged`, whether or not levels are set with this.
+pub struct PerformanceSettings {
+    xr_session: openxr::Session<openxr::Vulkan>,
+    fp: openxr::raw::PerformanceSettingsEXT,
+}
+
+impl PerformanceSettings {
+    /// Fails if `XR_EXT_performance_settings` isn't enabled on the instance.
+    pub fn new(xr_session: &openxr::Session<openxr::Vulkan>) -> Result<Self, Error> {
+        let fp = *xr_session
+            .instance()
+            .exts()
+            .ext_performance_settings
+            .as_ref()
+            .ok_or_else(|| Error::missing_extension("XR_EXT_performance_settings"))?;
+
+        Ok(Self {
+            xr_session: xr_session.clone(),
+            fp,
+        })
+    }
+
+    /// Hints the runtime what level of performance the application needs from a domain.
+    pub fn set_level(&self, domain: PerfDomain, level: PerfLevel) -> Result<(), Error> {
+        unsafe {
+            cvt((self.fp.perf_settings_set_performance_level)(
+                self.xr_session.as_raw(),
+                domain.xr_domain(),
+                level.xr_level(),
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Processor a performance level or notification applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerfDomain {
+    Cpu,
+    Gpu,
+}
+
+impl PerfDomain {
+    fn xr_domain(self) -> sys::PerfSettingsDomainEXT {
+        match self {
+            PerfDomain::Cpu => sys::PerfSettingsDomainEXT::CPU,
+            PerfDomain::Gpu => sys::PerfSettingsDomainEXT::GPU,
+        }
+    }
+
+    pub(crate) fn from_xr(domain: sys::PerfSettingsDomainEXT) -> Option<Self> {
+        match domain {
+            sys::PerfSettingsDomainEXT::CPU => Some(PerfDomain::Cpu),
+            sys::PerfSettingsDomainEXT::GPU => Some(PerfDomain::Gpu),
+            _ => None,
+        }
+    }
+}
+
+/// Level of performance the application asks for, from lowest to highest power use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PerfLevel {
+    /// Lowest power use, for when the application isn't doing much, like a loading screen.
+    PowerSavings,
+    /// Power use the device can sustain while staying comfortable for the user.
+    SustainedLow,
+    /// Power use the device can sustain for long periods, this is the default.
+    SustainedHigh,
+    /// Above sustainable power use, for short periods like level loads.
+    Boost,
+}
+
+impl PerfLevel {
+    fn xr_level(self) -> sys::PerfSettingsLevelEXT {
+        match self {
+            PerfLevel::PowerSavings => sys::PerfSettingsLevelEXT::POWER_SAVINGS,
+            PerfLevel::SustainedLow => sys::PerfSettingsLevelEXT::SUSTAINED_LOW,
+            PerfLevel::SustainedHigh => sys::PerfSettingsLevelEXT::SUSTAINED_HIGH,
+            PerfLevel::Boost => sys::PerfSettingsLevelEXT::BOOST,
+        }
+    }
+}
+
+/// Part of a domain a notification is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PerfSubDomain {
+    /// The runtime's compositor is missing its deadlines.
+    Compositing,
+    /// The application is missing its frame deadlines.
+    Rendering,
+    /// The device is heating up, and may throttle.
+    Thermal,
+}
+
+impl PerfSubDomain {
+    pub(crate) fn from_xr(sub_domain: sys::PerfSettingsSubDomainEXT) -> Option<Self> {
+        match sub_domain {
+            sys::PerfSettingsSubDomainEXT::COMPOSITING => Some(PerfSubDomain::Compositing),
+            sys::PerfSettingsSubDomainEXT::RENDERING => Some(PerfSubDomain::Rendering),
+            sys::PerfSettingsSubDomainEXT::THERMAL => Some(PerfSubDomain::Thermal),
+            _ => None,
+        }
+    }
+}
+
+/// How close a sub-domain is to its limits, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PerfNotificationLevel {
+    /// Within limits.
+    Normal,
+    /// Approaching limits, the application should lower its workload to avoid impairment.
+    Warning,
+    /// Past limits, the runtime is throttling or dropping frames.
+    Impaired,
+}
+
+impl PerfNotificationLevel {
+    pub(crate) fn from_xr(level: sys::PerfSettingsNotificationLevelEXT) -> Option<Self> {
+        match level {
+            sys::PerfSettingsNotificationLevelEXT::NORMAL => Some(PerfNotificationLevel::Normal),
+            sys::PerfSettingsNotificationLevelEXT::WARNING => Some(PerfNotificationLevel::Warning),
+            sys::PerfSettingsNotificationLevelEXT::IMPAIRED => {
+                Some(PerfNotificationLevel::Impaired)
+            }
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{Error, cvt};
+
+/// Display refresh rate of the headset, controlled with `XR_FB_display_refresh_rate`.
+///
+/// Without the extension the rate can't be changed, and is estimated from frame timing instead.
+/// Changes are reported as `SessionEvent::DisplayRefreshRateChanged`.
+pub struct DisplayRefreshRate {
+    xr_session: openxr::Session<openxr::Vulkan>,
+    fp: Option<openxr::raw::DisplayRefreshRateFB>,
+}
+
+impl DisplayRefreshRate {
+    pub fn new(xr_session: &openxr::Session<openxr::Vulkan>) -> Self {
+        Self {
+            xr_session: xr_session.clone(),
+            fp: xr_session.instance().exts().fb_display_refresh_rate,
+        }
+    }
+
+    /// If `XR_FB_display_refresh_rate` is enabled, and the rate can be queried and changed.
+    pub fn is_supported(&self) -> bool {
+        self.fp.is_some()
+    }
+
+    /// Refresh rates the display supports, in Hz. Empty if the extension isn't enabled.
+    pub fn supported_rates(&self) -> Result<Vec<f32>, Error> {
+        let Some(fp) = &self.fp else {
+            return Ok(Vec::new());
+        };
+
+        let mut count = 0;
+        unsafe {
+            cvt((fp.enumerate_display_refresh_rates)(
+                self.xr_session.as_raw(),
+                0,
+                &mut count,
+                std::ptr::null_mut(),
+            ))?;
+        }
+
+        let mut rates = vec![0.0; count as usize];
+        unsafe {
+            cvt((fp.enumerate_display_refresh_rates)(
+                self.xr_session.as_raw(),
+                rates.len() as u32,
+                &mut count,
+                rates.as_mut_ptr(),
+            ))?;
+        }
+        rates.truncate(count as usize);
+
+        Ok(rates)
+    }
+
+    /// Current refresh rate in Hz, `None` if the extension isn't enabled.
+    pub fn current(&self) -> Result<Option<f32>, Error> {
+        let Some(fp) = &self.fp else {
+            return Ok(None);
+        };
+
+        let mut rate = 0.0;
+        unsafe {
+            cvt((fp.get_display_refresh_rate)(
+                self.xr_session.as_raw(),
+                &mut rate,
+            ))?;
+        }
+
+        Ok(Some(rate))
+    }
+
+    /// Current refresh rate in Hz, estimated from a frame's predicted display period if the
+    /// extension isn't enabled.
+    pub fn current_or_estimate(
+        &self,
+        predicted_display_period: openxr::Duration,
+    ) -> Result<f32, Error> {
+        let rate = match self.current()? {
+            Some(rate) => rate,
+            None => refresh_rate_from_period(predicted_display_period),
+        };
+
+        Ok(rate)
+    }
+
+    /// Asks the runtime to switch to `rate`, which should be one of `supported_rates`.
+    ///
+    /// The switch happens asynchronously, and may not happen at all. Returns false without doing
+    /// anything if the extension isn't enabled.
+    pub fn request(&self, rate: f32) -> Result<bool, Error> {
+        let Some(fp) = &self.fp else {
+            return Ok(false);
+        };
+
+        unsafe {
+            cvt((fp.request_display_refresh_rate)(
+                self.xr_session.as_raw(),
+                rate,
+            ))?;
+        }
+
+        Ok(true)
+    }
+}
+
+/// Estimates the refresh rate in Hz from the time between frames, like
+/// `FrameState::predicted_display_period`.
+pub fn refresh_rate_from_period(period: openxr::Duration) -> f32 {
+    let nanos = period.as_nanos();
+    if nanos <= 0 {
+        return 0.0;
+    }
+
+    1_000_000_000.0 / nanos as f32
+}
@@ -0,0 +1,273 @@
+use crate::{Error, PerfDomain, PerfNotificationLevel, PerfSubDomain};
+
+/// Lifecycle state of an OpenXR session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionState {
+    Unknown,
+    Idle,
+    Ready,
+    Synchronized,
+    Visible,
+    Focused,
+    Stopping,
+    LossPending,
+    Exiting,
+}
+
+impl SessionState {
+    fn from_xr(state: openxr::SessionState) -> Self {
+        match state {
+            openxr::SessionState::IDLE => SessionState::Idle,
+            openxr::SessionState::READY => SessionState::Ready,
+            openxr::SessionState::SYNCHRONIZED => SessionState::Synchronized,
+            openxr::SessionState::VISIBLE => SessionState::Visible,
+            openxr::SessionState::FOCUSED => SessionState::Focused,
+            openxr::SessionState::STOPPING => SessionState::Stopping,
+            openxr::SessionState::LOSS_PENDING => SessionState::LossPending,
+            openxr::SessionState::EXITING => SessionState::Exiting,
+            _ => SessionState::Unknown,
+        }
+    }
+
+    /// If the session has begun, and frames should be submitted.
+    pub fn is_running(self) -> bool {
+        matches!(
+            self,
+            SessionState::Ready
+                | SessionState::Synchronized
+                | SessionState::Visible
+                | SessionState::Focused
+        )
+    }
+
+    /// If the application has input focus.
+    pub fn is_focused(self) -> bool {
+        self == SessionState::Focused
+    }
+
+    /// If the session is going away, and the application should exit.
+    pub fn should_exit(self) -> bool {
+        matches!(self, SessionState::LossPending | SessionState::Exiting)
+    }
+}
+
+/// Event from the OpenXR runtime, that isn't handled by `SessionDriver` itself.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum SessionEvent {
+    /// The session changed state.
+    ///
+    /// Required transitions, like beginning and ending the session, have already been performed
+    /// when this is received.
+    StateChanged {
+        state: SessionState,
+    },
+    ReferenceSpaceChangePending {
+        reference_space_type: openxr::ReferenceSpaceType,
+        change_time: openxr::Time,
+        pose_valid: bool,
+        pose_in_previous_space: openxr::Posef,
+    },
+    InteractionProfileChanged,
+    VisibilityMaskChanged {
+        view_configuration_type: openxr::ViewConfigurationType,
+        view_index: u32,
+    },
+    /// The display switched refresh rate, see `DisplayRefreshRate`.
+    DisplayRefreshRateChanged {
+        from_display_refresh_rate: f32,
+        to_display_refresh_rate: f32,
+    },
+    /// A performance sub-domain changed notification level, see `PerformanceSettings`.
+    ///
+    /// Lowering the workload on a warning gives the runtime room before it has to throttle.
+    PerfSettingsChanged {
+        domain: PerfDomain,
+        sub_domain: PerfSubDomain,
+        from_level: PerfNotificationLevel,
+        to_level: PerfNotificationLevel,
+    },
+    /// The runtime's event queue overflowed, and events were lost.
+    EventsLost {
+        count: u32,
+    },
+}
+
+/// Drives an OpenXR session through its lifecycle.
+///
+/// Every application has to poll the runtime for events, and begin and end the session when the
+/// runtime asks for it. The driver does this, leaving the remaining events to the application.
+pub struct SessionDriver {
+    xr_instance: openxr::Instance,
+    xr_session: openxr::Session<openxr::Vulkan>,
+    view_type: openxr::ViewConfigurationType,
+    event_storage: openxr::EventDataBuffer,
+    state: SessionState,
+    instance_loss_pending: bool,
+    exit_requested: bool,
+}
+
+impl SessionDriver {
+    /// Creates a driver for the session, which will be begun using `view_type`.
+    pub fn new(
+        xr_session: openxr::Session<openxr::Vulkan>,
+        view_type: openxr::ViewConfigurationType,
+    ) -> Self {
+        Self {
+            xr_instance: xr_session.instance().clone(),
+            xr_session,
+            view_type,
+            event_storage: openxr::EventDataBuffer::new(),
+            state: SessionState::Unknown,
+            instance_loss_pending: false,
+            exit_requested: false,
+        }
+    }
+
+    pub fn session(&self) -> &openxr::Session<openxr::Vulkan> {
+        &self.xr_session
+    }
+
+    pub fn state(&self) -> SessionState {
+        self.state
+    }
+
+    /// See `SessionState::is_running`.
+    pub fn is_running(&self) -> bool {
+        self.state.is_running()
+    }
+
+    /// See `SessionState::is_focused`.
+    pub fn is_focused(&self) -> bool {
+        self.state.is_focused()
+    }
+
+    /// If the application should exit, because the session or instance is going away.
+    pub fn should_exit(&self) -> bool {
+        self.state.should_exit() || self.instance_loss_pending || self.exit_requested
+    }
+
+    /// Asks the runtime to end the session.
+    ///
+    /// The runtime may want to perform a smooth transition between scenes, so the session isn't
+    /// done immediately. Keep polling events until `should_exit` returns true.
+    pub fn request_exit(&mut self) -> Result<(), Error> {
+        match self.xr_session.request_exit() {
+            Ok(()) => {}
+            // The session isn't running, so there's nothing to wait for
+            Err(openxr::sys::Result::ERROR_SESSION_NOT_RUNNING) => self.exit_requested = true,
+            Err(error) => return Err(error.into()),
+        }
+
+        Ok(())
+    }
+
+    /// Polls the runtime for events.
+    ///
+    /// State changes are handled while iterating, so the iterator should be drained every frame.
+    /// The iterator ends after yielding an error, poll again next frame to retry.
+    pub fn poll_events(&mut self) -> SessionEvents<'_> {
+        SessionEvents {
+            driver: self,
+            failed: false,
+        }
+    }
+
+    fn next_event(&mut self) -> Result<Option<SessionEvent>, Error> {
+        use openxr::Event;
+
+        loop {
+            let Some(event) = self.xr_instance.poll_event(&mut self.event_storage)? else {
+                return Ok(None);
+            };
+
+            let event = match event {
+                Event::SessionStateChanged(event) => {
+                    let state = SessionState::from_xr(event.state());
+
+                    // These transitions are mandatory, the runtime waits for us to perform them
+                    match state {
+                        SessionState::Ready => {
+                            self.xr_session.begin(self.view_type)?;
+                        }
+                        SessionState::Stopping => {
+                            self.xr_session.end()?;
+                        }
+                        _ => {}
+                    }
+
+                    self.state = state;
+                    SessionEvent::StateChanged { state }
+                }
+                Event::InstanceLossPending(_) => {
+                    self.instance_loss_pending = true;
+                    continue;
+                }
+                Event::ReferenceSpaceChangePending(event) => {
+                    SessionEvent::ReferenceSpaceChangePending {
+                        reference_space_type: event.reference_space_type(),
+                        change_time: event.change_time(),
+                        pose_valid: event.pose_valid(),
+                        pose_in_previous_space: event.pose_in_previous_space(),
+                    }
+                }
+                Event::InteractionProfileChanged(_) => SessionEvent::InteractionProfileChanged,
+                Event::VisibilityMaskChangedKHR(event) => SessionEvent::VisibilityMaskChanged {
+                    view_configuration_type: event.view_configuration_type(),
+                    view_index: event.view_index(),
+                },
+                Event::DisplayRefreshRateChangedFB(event) => {
+                    SessionEvent::DisplayRefreshRateChanged {
+                        from_display_refresh_rate: event.from_display_refresh_rate(),
+                        to_display_refresh_rate: event.to_display_refresh_rate(),
+                    }
+                }
+                Event::PerfSettingsEXT(event) => {
+                    // Values from newer versions of the extension can't be represented
+                    let (Some(domain), Some(sub_domain), Some(from_level), Some(to_level)) = (
+                        PerfDomain::from_xr(event.domain()),
+                        PerfSubDomain::from_xr(event.sub_domain()),
+                        PerfNotificationLevel::from_xr(event.from_level()),
+                        PerfNotificationLevel::from_xr(event.to_level()),
+                    ) else {
+                        continue;
+                    };
+
+                    SessionEvent::PerfSettingsChanged {
+                        domain,
+                        sub_domain,
+                        from_level,
+                        to_level,
+                    }
+                }
+                Event::EventsLost(event) => SessionEvent::EventsLost {
+                    count: event.lost_event_count(),
+                },
+                _ => continue,
+            };
+
+            return Ok(Some(event));
+        }
+    }
+}
+
+/// Iterator over events polled by `SessionDriver::poll_events`.
+pub struct SessionEvents<'a> {
+    driver: &'a mut SessionDriver,
+    failed: bool,
+}
+
+impl Iterator for SessionEvents<'_> {
+    type Item = Result<SessionEvent, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        // Errors like a lost instance persist, polling again would yield them forever
+        let result = self.driver.next_event().transpose();
+        self.failed = matches!(result, Some(Err(_)));
+        result
+    }
+}
@@ -0,0 +1,183 @@
+use crate::{Error, SessionEvent};
+
+/// Kind of reference space to track the user in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceSpaceKind {
+    /// Room-scale space, with the origin on the floor at the center of the play area.
+    Stage,
+    /// Space with the origin on the floor below the user's starting position.
+    ///
+    /// Uses `XR_EXT_local_floor` when the runtime supports it, and is emulated from `Local` and
+    /// the height of the stage's floor otherwise.
+    LocalFloor,
+    /// Seated space, with the origin at the user's starting head position.
+    Local,
+    /// Space locked to the user's head.
+    View,
+}
+
+impl ReferenceSpaceKind {
+    fn xr_type(self) -> openxr::ReferenceSpaceType {
+        match self {
+            ReferenceSpaceKind::Stage => openxr::ReferenceSpaceType::STAGE,
+            ReferenceSpaceKind::LocalFloor => openxr::ReferenceSpaceType::LOCAL_FLOOR,
+            ReferenceSpaceKind::Local => openxr::ReferenceSpaceType::LOCAL,
+            ReferenceSpaceKind::View => openxr::ReferenceSpaceType::VIEW,
+        }
+    }
+}
+
+/// Reference space the application tracks the user in, picked by preference.
+pub struct TrackingSpace {
+    xr_session: openxr::Session<openxr::Vulkan>,
+    kind: ReferenceSpaceKind,
+    space: openxr::Space,
+    /// Set when local floor is emulated, and the floor height has to be derived from the stage.
+    emulated: Option<EmulatedFloor>,
+}
+
+struct EmulatedFloor {
+    stage: openxr::Space,
+    local: openxr::Space,
+    needs_update: bool,
+}
+
+impl TrackingSpace {
+    /// Creates the first space in `preferred` the runtime supports.
+    ///
+    /// Local floor is emulated if the runtime doesn't support it, but does support a stage.
+    pub fn new(
+        xr_session: &openxr::Session<openxr::Vulkan>,
+        preferred: &[ReferenceSpaceKind],
+    ) -> Result<Self, Error> {
+        let supported = xr_session.enumerate_reference_spaces()?;
+
+        for &kind in preferred {
+            if supported.contains(&kind.xr_type()) {
+                let space =
+                    xr_session.create_reference_space(kind.xr_type(), openxr::Posef::IDENTITY)?;
+
+                return Ok(Self {
+                    xr_session: xr_session.clone(),
+                    kind,
+                    space,
+                    emulated: None,
+                });
+            }
+
+            let can_emulate = kind == ReferenceSpaceKind::LocalFloor
+                && supported.contains(&openxr::ReferenceSpaceType::STAGE)
+                && supported.contains(&openxr::ReferenceSpaceType::LOCAL);
+            if can_emulate {
+                let create_space =
+                    |ty| xr_session.create_reference_space(ty, openxr::Posef::IDENTITY);
+
+                // Until the floor height is known, this is the same as local
+                return Ok(Self {
+                    xr_session: xr_session.clone(),
+                    kind,
+                    space: create_space(openxr::ReferenceSpaceType::LOCAL)?,
+                    emulated: Some(EmulatedFloor {
+                        stage: create_space(openxr::ReferenceSpaceType::STAGE)?,
+                        local: create_space(openxr::ReferenceSpaceType::LOCAL)?,
+                        needs_update: true,
+                    }),
+                });
+            }
+        }
+
+        Err(Error::UnsupportedReferenceSpaces(preferred.to_vec()))
+    }
+
+    pub fn kind(&self) -> ReferenceSpaceKind {
+        self.kind
+    }
+
+    /// If local floor is emulated, rather than provided by the runtime.
+    pub fn is_emulated(&self) -> bool {
+        self.emulated.is_some()
+    }
+
+    /// The space to locate views, controllers, etc. in.
+    ///
+    /// For emulated local floor the space is recreated when the floor height changes, so don't
+    /// hold on to it across frames.
+    pub fn space(&self) -> &openxr::Space {
+        &self.space
+    }
+
+    /// Size of the play area, centered on the stage's origin.
+    ///
+    /// `None` if the runtime doesn't know the bounds, for example when there's no calibrated
+    /// play area.
+    pub fn stage_bounds(&self) -> Result<Option<openxr::Extent2Df>, Error> {
+        let bounds = self
+            .xr_session
+            .reference_space_bounds_rect(openxr::ReferenceSpaceType::STAGE)?;
+
+        Ok(bounds)
+    }
+
+    /// Updates spaces derived from others, call this once per frame with its predicted display
+    /// time.
+    ///
+    /// Returns if the space was recreated, in which case anything located in the old space should
+    /// be located again.
+    pub fn update(&mut self, time: openxr::Time) -> Result<bool, Error> {
+        let Some(emulated) = &mut self.emulated else {
+            return Ok(false);
+        };
+        if !emulated.needs_update {
+            return Ok(false);
+        }
+
+        // The stage's origin is on the floor, so its height in local is the floor height
+        let location = emulated.stage.locate(&emulated.local, time)?;
+        if !location
+            .location_flags
+            .contains(openxr::SpaceLocationFlags::POSITION_VALID)
+        {
+            return Ok(false);
+        }
+
+        let pose = openxr::Posef {
+            orientation: openxr::Quaternionf::IDENTITY,
+            position: openxr::Vector3f {
+                x: 0.0,
+                y: location.pose.position.y,
+                z: 0.0,
+            },
+        };
+        self.space = self
+            .xr_session
+            .create_reference_space(openxr::ReferenceSpaceType::LOCAL, pose)?;
+        emulated.needs_update = false;
+
+        Ok(true)
+    }
+
+    /// Handles events from `SessionDriver::poll_events`.
+    ///
+    /// Returns if the tracking space is about to change, and spaces or content placed relative to
+    /// it should be derived again. The runtime moves spaces it provides itself, emulated local
+    /// floor is recreated on the next `update`.
+    pub fn handle_event(&mut self, event: &SessionEvent) -> bool {
+        let SessionEvent::ReferenceSpaceChangePending {
+            reference_space_type,
+            ..
+        } = *event
+        else {
+            return false;
+        };
+
+        match &mut self.emulated {
+            Some(emulated) => {
+                let affected = reference_space_type == openxr::ReferenceSpaceType::LOCAL
+                    || reference_space_type == openxr::ReferenceSpaceType::STAGE;
+                emulated.needs_update |= affected;
+                affected
+            }
+            None => reference_space_type == self.kind.xr_type(),
+        }
+    }
+}
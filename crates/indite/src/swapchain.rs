@@ -1,86 +1,568 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    marker::PhantomData,
+    sync::{Arc, Mutex, MutexGuard},
+};
 
-use anyhow::{Context, Error};
 use ash::vk::{self, Handle};
+use openxr::sys;
 use wgpu::{
     Device, Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
     TextureUses, TextureView, TextureViewDescriptor, TextureViewDimension,
     hal::{Api, api::Vulkan, vulkan::TextureMemory},
 };
 
+use crate::{
+    Error, FoveationDescriptor, FragmentDensityMap, cvt,
+    format::select_format,
+    foveation::{
+        apply_foveation, check_foveation_support, create_foveated_swapchain,
+        enumerate_fragment_density_maps,
+    },
+};
+
+#[derive(Debug, Clone)]
 pub struct SwapchainDescriptor {
     pub width: u32,
     pub height: u32,
     pub view_count: u32,
+    /// Formats to pick from, in order of preference.
+    ///
+    /// The first format supported by the runtime is picked. If empty, the runtime's own most
+    /// preferred format is used.
+    pub formats: Vec<TextureFormat>,
+    /// Fixed foveated rendering to apply, see `FoveationDescriptor`.
+    ///
+    /// Requires `XR_FB_foveation`, `XR_FB_foveation_configuration`, `XR_FB_foveation_vulkan` and
+    /// `XR_FB_swapchain_update_state` on the instance, and `VK_EXT_fragment_density_map` on the
+    /// device. Creating the swapchain fails if any of them isn't enabled.
+    pub foveation: Option<FoveationDescriptor>,
+}
+
+pub type SwapchainHandle<G = openxr::Vulkan> = Arc<Mutex<openxr::Swapchain<G>>>;
+
+/// OpenXR swapchain, with a WGPU texture for each of its images.
+pub struct Swapchain {
+    handle: SwapchainHandle,
+    raw: openxr::sys::Swapchain,
+    desc: SwapchainDescriptor,
+    kind: SwapchainKind,
+    format: TextureFormat,
+    textures: Vec<(Texture, TextureView)>,
+    fragment_density_maps: Vec<FragmentDensityMap>,
+}
+
+impl Swapchain {
+    /// The swapchain itself is behind a mutex, because the WGPU textures that reference it need to
+    /// keep it alive.
+    pub fn handle(&self) -> &SwapchainHandle {
+        &self.handle
+    }
+
+    /// Raw OpenXR handle of the swapchain, valid for as long as this `Swapchain` is alive.
+    pub fn as_raw(&self) -> openxr::sys::Swapchain {
+        self.raw
+    }
+
+    pub fn desc(&self) -> &SwapchainDescriptor {
+        &self.desc
+    }
+
+    /// Format negotiated with the runtime, create pipelines rendering to this swapchain with it.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
+    pub(crate) fn kind(&self) -> SwapchainKind {
+        self.kind
+    }
+
+    /// Textures for every image in the swapchain, by image index.
+    pub fn textures(&self) -> &[(Texture, TextureView)] {
+        &self.textures
+    }
+
+    /// Acquires the next image from the runtime.
+    ///
+    /// Which image you get is up to the runtime. The image has to be waited on before it can be
+    /// rendered to, see `SwapchainImage::wait`. Only one image can be acquired at a time, the
+    /// previous one has to be released first.
+    pub fn acquire(&mut self) -> Result<SwapchainImage<'_>, Error> {
+        acquire_image(&self.handle, &self.textures, &self.fragment_density_maps)
+    }
+
+    /// Recreates the swapchain at a new resolution, keeping its format and view count.
+    ///
+    /// Use this when the runtime's recommended resolution changes, or for dynamic resolution.
+    /// Pipelines created against `format` remain valid.
+    ///
+    /// The old swapchain is destroyed once WGPU is done with its textures, so work that's still in
+    /// flight can keep using them. Texture views obtained from the old swapchain should be dropped
+    /// by the caller.
+    pub fn recreate(
+        &mut self,
+        device: &Device,
+        xr_session: &openxr::Session<openxr::Vulkan>,
+        width: u32,
+        height: u32,
+    ) -> Result<(), Error> {
+        let desc = SwapchainDescriptor {
+            width,
+            height,
+            // Pin the format, so it doesn't change from under the application
+            formats: vec![self.format],
+            ..self.desc.clone()
+        };
+        let mut swapchain = create_swapchain_of_kind(device, xr_session, &desc, self.kind)?;
+
+        // Report the formats the application asked for, not the pinned one
+        swapchain.desc.formats = std::mem::take(&mut self.desc.formats);
+
+        // Dropping the old textures releases their references to the old handle
+        *self = swapchain;
+
+        Ok(())
+    }
+
+    /// Changes the foveation of a swapchain created with `SwapchainDescriptor::foveation`.
+    ///
+    /// This can be changed every frame, for example to lower the level when performance allows.
+    pub fn set_foveation(
+        &mut self,
+        xr_session: &openxr::Session<openxr::Vulkan>,
+        foveation: FoveationDescriptor,
+    ) -> Result<(), Error> {
+        if self.desc.foveation.is_none() {
+            return Err(Error::FoveationNotEnabled);
+        }
+
+        apply_foveation(xr_session, self.raw, &foveation)?;
+        self.desc.foveation = Some(foveation);
+
+        Ok(())
+    }
+}
+
+/// State of a `SwapchainImage` that has been acquired, but may still be in use by the runtime.
+pub struct ImageAcquired;
+
+/// State of a `SwapchainImage` that has been waited on, and can be rendered to.
+pub struct ImageWaited;
+
+/// State of a `SwapchainImage`, implemented by `ImageAcquired` and `ImageWaited`.
+pub trait ImageState: private::Sealed {
+    /// If the image has been waited on, and can be released.
+    const WAITED: bool;
+}
+
+impl ImageState for ImageAcquired {
+    const WAITED: bool = false;
+}
+
+impl ImageState for ImageWaited {
+    const WAITED: bool = true;
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for super::ImageAcquired {}
+    impl Sealed for super::ImageWaited {}
+}
+
+/// Guard over an image acquired from a `Swapchain`.
+///
+/// The image's texture is only available after waiting on it, as the compositor could still be
+/// reading from it until then. The image is released back to the runtime when the guard is dropped,
+/// waiting on it first if that hasn't happened yet. Use `release` to handle errors from releasing.
+///
+/// `G` is the graphics API of the session, only other than Vulkan for the `gles` backend.
+pub struct SwapchainImage<'a, S: ImageState = ImageAcquired, G: openxr::Graphics = openxr::Vulkan> {
+    handle: &'a SwapchainHandle<G>,
+    textures: &'a [(Texture, TextureView)],
+    fragment_density_maps: &'a [FragmentDensityMap],
+    index: u32,
+    _state: PhantomData<S>,
+}
+
+/// Outcome of `SwapchainImage::wait`.
+pub enum ImageWait<'a, G: openxr::Graphics = openxr::Vulkan> {
+    /// The image can be rendered to.
+    Ready(SwapchainImage<'a, ImageWaited, G>),
+    /// The timeout passed before the image became available, it can be waited on again.
+    TimedOut(SwapchainImage<'a, ImageAcquired, G>),
+}
+
+/// Acquires the next image of a swapchain, see `Swapchain::acquire`.
+pub(crate) fn acquire_image<'a, G: openxr::Graphics>(
+    handle: &'a SwapchainHandle<G>,
+    textures: &'a [(Texture, TextureView)],
+    fragment_density_maps: &'a [FragmentDensityMap],
+) -> Result<SwapchainImage<'a, ImageAcquired, G>, Error> {
+    let index = handle
+        .lock()
+        .map_err(|_| Error::SwapchainPoisoned)?
+        .acquire_image()?;
+
+    Ok(SwapchainImage {
+        handle,
+        textures,
+        fragment_density_maps,
+        index,
+        _state: PhantomData,
+    })
 }
 
-pub type SwapchainHandle = Arc<Mutex<openxr::Swapchain<openxr::Vulkan>>>;
+impl<S: ImageState, G: openxr::Graphics> SwapchainImage<'_, S, G> {
+    /// Index of the image in the swapchain.
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, openxr::Swapchain<G>>, Error> {
+        self.handle.lock().map_err(|_| Error::SwapchainPoisoned)
+    }
+}
+
+impl<'a, G: openxr::Graphics> SwapchainImage<'a, ImageAcquired, G> {
+    /// Blocks until the image is available to render to, or until `timeout` has passed.
+    ///
+    /// If waiting fails the image stays acquired, as the runtime doesn't allow releasing it.
+    pub fn wait(self, timeout: openxr::Duration) -> Result<ImageWait<'a, G>, Error> {
+        let result = self
+            .lock()
+            .and_then(|xr_swapchain| wait_image(&xr_swapchain, timeout));
+
+        match result {
+            Ok(true) => {}
+            Ok(false) => return Ok(ImageWait::TimedOut(self)),
+            Err(error) => {
+                std::mem::forget(self);
+                return Err(error);
+            }
+        }
+
+        let image = SwapchainImage {
+            handle: self.handle,
+            textures: self.textures,
+            fragment_density_maps: self.fragment_density_maps,
+            index: self.index,
+            _state: PhantomData,
+        };
+
+        // Ownership of the acquired image moves to the waited guard
+        std::mem::forget(self);
+
+        Ok(ImageWait::Ready(image))
+    }
+
+    /// Blocks until the image is available to render to, without a timeout.
+    pub fn wait_infinite(self) -> Result<SwapchainImage<'a, ImageWaited, G>, Error> {
+        match self.wait(openxr::Duration::INFINITE)? {
+            ImageWait::Ready(image) => Ok(image),
+            ImageWait::TimedOut(_) => unreachable!("waiting without a timeout can't time out"),
+        }
+    }
+}
+
+impl<G: openxr::Graphics> SwapchainImage<'_, ImageWaited, G> {
+    pub fn texture(&self) -> &Texture {
+        &self.textures[self.index as usize].0
+    }
+
+    pub fn view(&self) -> &TextureView {
+        &self.textures[self.index as usize].1
+    }
+
+    /// Density map to attach when rendering to the image, if the swapchain is foveated.
+    pub fn fragment_density_map(&self) -> Option<FragmentDensityMap> {
+        self.fragment_density_maps.get(self.index as usize).copied()
+    }
+
+    /// Releases the image back to the runtime.
+    ///
+    /// Work rendering to the image must have been submitted before releasing it.
+    pub fn release(self) -> Result<(), Error> {
+        let result = self
+            .lock()
+            .and_then(|xr_swapchain| release_image(&xr_swapchain));
+
+        // Whether it succeeded or not, we shouldn't try releasing again
+        std::mem::forget(self);
+
+        result
+    }
+}
+
+impl<S: ImageState, G: openxr::Graphics> Drop for SwapchainImage<'_, S, G> {
+    fn drop(&mut self) {
+        let Ok(xr_swapchain) = self.lock() else {
+            return;
+        };
+
+        // The runtime requires images to be waited on before they can be released, if that fails
+        // the image has to stay acquired
+        if !S::WAITED
+            && !matches!(
+                wait_image(&xr_swapchain, openxr::Duration::INFINITE),
+                Ok(true)
+            )
+        {
+            return;
+        }
+        let _ = release_image(&xr_swapchain);
+    }
+}
+
+/// Waits on the oldest acquired image, returning false if `timeout` passed first.
+///
+/// openxr's `wait_image` counts a timeout as waited, and its `wait_image` and `release_image`
+/// panic when their order goes wrong, so the runtime is called directly instead.
+fn wait_image<G: openxr::Graphics>(
+    xr_swapchain: &openxr::Swapchain<G>,
+    timeout: openxr::Duration,
+) -> Result<bool, Error> {
+    let info = sys::SwapchainImageWaitInfo {
+        ty: sys::SwapchainImageWaitInfo::TYPE,
+        next: std::ptr::null_mut(),
+        timeout,
+    };
+    let result = unsafe {
+        cvt((xr_swapchain.instance().fp().wait_swapchain_image)(
+            xr_swapchain.as_raw(),
+            &info,
+        ))?
+    };
+
+    Ok(result != sys::Result::TIMEOUT_EXPIRED)
+}
+
+/// Releases the oldest waited image, see `wait_image`.
+fn release_image<G: openxr::Graphics>(xr_swapchain: &openxr::Swapchain<G>) -> Result<(), Error> {
+    unsafe {
+        cvt((xr_swapchain.instance().fp().release_swapchain_image)(
+            xr_swapchain.as_raw(),
+            std::ptr::null(),
+        ))?;
+    }
+
+    Ok(())
+}
 
 /// Creates a swapchain for the OpenXR session.
 ///
-/// The swapchain itself is returned with a mutex guard, because the WGPU textures that reference it
-/// need to keep it alive.
+/// The format is negotiated with the runtime from `desc.formats`, and is available from the
+/// returned swapchain so pipelines can be created against it.
 pub fn create_swapchain(
     device: &Device,
     xr_session: &openxr::Session<openxr::Vulkan>,
     desc: &SwapchainDescriptor,
-) -> Result<(SwapchainHandle, Vec<(Texture, TextureView)>), Error> {
+) -> Result<Swapchain, Error> {
+    create_swapchain_of_kind(device, xr_session, desc, SwapchainKind::Color)
+}
+
+/// Creates a depth swapchain for the OpenXR session.
+///
+/// Works the same as `create_swapchain`, but only depth formats are considered. If `desc.formats`
+/// is empty, `Depth24PlusStencil8` and `Depth32Float` are tried in that order.
+///
+/// Submit the result to the runtime with `attach_depth_infos`, so it can use it for reprojection.
+pub fn create_depth_swapchain(
+    device: &Device,
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    desc: &SwapchainDescriptor,
+) -> Result<Swapchain, Error> {
+    create_swapchain_of_kind(device, xr_session, desc, SwapchainKind::Depth)
+}
+
+/// Creates a cube map swapchain for the OpenXR session, for use with cube layers.
+///
+/// Works the same as `create_swapchain`, but every image is a cube with six faces, in the usual
+/// +X, -X, +Y, -Y, +Z, -Z order. `desc.view_count` is ignored, and `desc.width` and `desc.height`
+/// must be equal.
+///
+/// The image views are 2D arrays with a layer per face, as cube views can't be rendered to. Render
+/// to a single face through a `D2` view with `base_array_layer` set to the face's index.
+pub fn create_cube_swapchain(
+    device: &Device,
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    desc: &SwapchainDescriptor,
+) -> Result<Swapchain, Error> {
+    let desc = SwapchainDescriptor {
+        view_count: 1,
+        ..desc.clone()
+    };
+    create_swapchain_of_kind(device, xr_session, &desc, SwapchainKind::Cube)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SwapchainKind {
+    Color,
+    Depth,
+    Cube,
+}
+
+impl SwapchainKind {
+    fn accepts(self, format: TextureFormat) -> bool {
+        match self {
+            SwapchainKind::Color | SwapchainKind::Cube => !format.is_depth_stencil_format(),
+            SwapchainKind::Depth => format.is_depth_stencil_format(),
+        }
+    }
+
+    fn default_formats(self) -> &'static [TextureFormat] {
+        match self {
+            // Empty means we use whatever the runtime prefers
+            SwapchainKind::Color | SwapchainKind::Cube => &[],
+            SwapchainKind::Depth => &[
+                TextureFormat::Depth24PlusStencil8,
+                TextureFormat::Depth32Float,
+            ],
+        }
+    }
+
+    fn xr_usage(self) -> openxr::SwapchainUsageFlags {
+        match self {
+            SwapchainKind::Color | SwapchainKind::Cube => {
+                openxr::SwapchainUsageFlags::COLOR_ATTACHMENT | openxr::SwapchainUsageFlags::SAMPLED
+            }
+            SwapchainKind::Depth => openxr::SwapchainUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+        }
+    }
+
+    fn hal_usage(self) -> TextureUses {
+        match self {
+            SwapchainKind::Color | SwapchainKind::Cube => {
+                TextureUses::COLOR_TARGET | TextureUses::COPY_DST
+            }
+            SwapchainKind::Depth => {
+                TextureUses::DEPTH_STENCIL_READ | TextureUses::DEPTH_STENCIL_WRITE
+            }
+        }
+    }
+
+    fn usage(self) -> TextureUsages {
+        match self {
+            SwapchainKind::Color | SwapchainKind::Cube => {
+                TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_DST
+            }
+            SwapchainKind::Depth => TextureUsages::RENDER_ATTACHMENT,
+        }
+    }
+
+    fn face_count(self) -> u32 {
+        match self {
+            SwapchainKind::Color | SwapchainKind::Depth => 1,
+            SwapchainKind::Cube => 6,
+        }
+    }
+
+    /// Array layers of the WGPU textures, cube faces are exposed as array layers.
+    fn texture_layers(self, desc: &SwapchainDescriptor) -> u32 {
+        desc.view_count * self.face_count()
+    }
+}
+
+fn create_swapchain_of_kind(
+    device: &Device,
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    desc: &SwapchainDescriptor,
+    kind: SwapchainKind,
+) -> Result<Swapchain, Error> {
+    if kind == SwapchainKind::Cube && desc.width != desc.height {
+        return Err(Error::CubeNotSquare {
+            width: desc.width,
+            height: desc.height,
+        });
+    }
+
+    let preferred = if desc.formats.is_empty() {
+        kind.default_formats()
+    } else {
+        &desc.formats
+    };
+
+    let runtime_formats: Vec<_> = xr_session
+        .enumerate_swapchain_formats()?
+        .into_iter()
+        .map(|format| vk::Format::from_raw(format as _))
+        .collect();
+    let (vk_format, format) =
+        select_format(&runtime_formats, preferred, |format| kind.accepts(format))?;
+
     let swapchain_resolution = vk::Extent2D {
         width: desc.width,
         height: desc.height,
     };
     let swapchain_info = openxr::SwapchainCreateInfo {
         create_flags: openxr::SwapchainCreateFlags::EMPTY,
-        usage_flags: openxr::SwapchainUsageFlags::COLOR_ATTACHMENT
-            | openxr::SwapchainUsageFlags::SAMPLED,
-        format: vk::Format::R8G8B8A8_SRGB.as_raw() as _,
+        usage_flags: kind.xr_usage(),
+        format: vk_format.as_raw() as _,
         sample_count: 1,
         width: swapchain_resolution.width,
         height: swapchain_resolution.height,
-        face_count: 1,
+        face_count: kind.face_count(),
         array_size: desc.view_count,
         mip_count: 1,
     };
-    let xr_swapchain = xr_session.create_swapchain(&swapchain_info)?;
+    let (xr_swapchain, fragment_density_maps) = match &desc.foveation {
+        Some(foveation) => {
+            check_foveation_support(xr_session.instance(), device)?;
+
+            let xr_swapchain = create_foveated_swapchain(xr_session, &swapchain_info)?;
+            apply_foveation(xr_session, xr_swapchain.as_raw(), foveation)?;
+            let fragment_density_maps = enumerate_fragment_density_maps(&xr_swapchain)?;
+            (xr_swapchain, fragment_density_maps)
+        }
+        None => (xr_session.create_swapchain(&swapchain_info)?, Vec::new()),
+    };
+    let raw = xr_swapchain.as_raw();
     let xr_swapchain_handle = Arc::new(Mutex::new(xr_swapchain));
 
-    let swapchain_textures = create_swapchain_textures(device, desc, &xr_swapchain_handle)?;
+    let swapchain_textures =
+        create_swapchain_textures(device, desc, kind, format, &xr_swapchain_handle)?;
 
-    Ok((xr_swapchain_handle, swapchain_textures))
+    Ok(Swapchain {
+        handle: xr_swapchain_handle,
+        raw,
+        desc: desc.clone(),
+        kind,
+        format,
+        textures: swapchain_textures,
+        fragment_density_maps,
+    })
 }
 
 fn create_swapchain_textures(
     device: &Device,
     desc: &SwapchainDescriptor,
+    kind: SwapchainKind,
+    format: TextureFormat,
     xr_swapchain_handle: &SwapchainHandle,
 ) -> Result<Vec<(Texture, TextureView)>, Error> {
-    let hal_device = unsafe {
-        device
-            .as_hal::<Vulkan>()
-            .context("wgpu device backend not vulkan")?
-    };
+    let hal_device = unsafe { device.as_hal::<Vulkan>().ok_or(Error::NotVulkan)? };
 
     let xr_swapchain = xr_swapchain_handle
         .lock()
-        .ok()
-        .context("failed to lock swapchain")?;
-    let swapchain_images = xr_swapchain.enumerate_images().unwrap();
+        .map_err(|_| Error::SwapchainPoisoned)?;
+    let swapchain_images = xr_swapchain.enumerate_images()?;
 
     let swapchain_textures: Vec<_> = swapchain_images
         .into_iter()
-        .map(|color_image| {
+        .map(|image| {
             let texture = unsafe {
                 create_swapchain_texture(
                     device,
                     &hal_device,
                     desc,
+                    kind,
+                    format,
                     xr_swapchain_handle.clone(),
-                    color_image,
+                    image,
                 )
             };
             let view = texture.create_view(&TextureViewDescriptor {
                 dimension: Some(TextureViewDimension::D2Array),
-                array_layer_count: Some(desc.view_count),
+                array_layer_count: Some(kind.texture_layers(desc)),
                 ..Default::default()
             });
             (texture, view)
@@ -91,30 +573,32 @@ fn create_swapchain_textures(
 }
 
 /// # Safety
-/// - `color_image` must be valid for the information in `desc`.
-/// - `color_image` lifetime is not managed by the returned `Texture`.
-/// - `color_image` must be valid for as long as `xr_swapchain` is valid.
+/// - `image` must be valid for the information in `desc`, `kind` and `format`.
+/// - `image` lifetime is not managed by the returned `Texture`.
+/// - `image` must be valid for as long as `xr_swapchain` is valid.
 unsafe fn create_swapchain_texture(
     device: &Device,
     hal_device: &<Vulkan as Api>::Device,
     desc: &SwapchainDescriptor,
+    kind: SwapchainKind,
+    format: TextureFormat,
     xr_swapchain_handle: SwapchainHandle,
-    color_image: u64,
+    image: u64,
 ) -> Texture {
-    let color_image = vk::Image::from_raw(color_image);
+    let image = vk::Image::from_raw(image);
 
     let hal_texture_desc = wgpu::hal::TextureDescriptor {
         label: Some("openxr swapchain texture"),
         size: Extent3d {
             width: desc.width,
             height: desc.height,
-            depth_or_array_layers: desc.view_count,
+            depth_or_array_layers: kind.texture_layers(desc),
         },
         mip_level_count: 1,
         sample_count: 1,
         dimension: TextureDimension::D2,
-        format: TextureFormat::Rgba8UnormSrgb,
-        usage: TextureUses::COLOR_TARGET | TextureUses::COPY_DST,
+        format,
+        usage: kind.hal_usage(),
         memory_flags: wgpu::hal::MemoryFlags::empty(),
         view_formats: Vec::new(),
     };
@@ -129,7 +613,7 @@ unsafe fn create_swapchain_texture(
 
     let wgpu_hal_texture = unsafe {
         hal_device.texture_from_raw(
-            color_image,
+            image,
             &hal_texture_desc,
             Some(Box::new(drop_callback)),
             TextureMemory::External,
@@ -141,13 +625,13 @@ unsafe fn create_swapchain_texture(
         size: Extent3d {
             width: desc.width,
             height: desc.height,
-            depth_or_array_layers: desc.view_count,
+            depth_or_array_layers: kind.texture_layers(desc),
         },
         mip_level_count: 1,
         sample_count: 1,
         dimension: TextureDimension::D2,
-        format: TextureFormat::Rgba8UnormSrgb,
-        usage: TextureUsages::RENDER_ATTACHMENT | TextureUsages::COPY_DST,
+        format,
+        usage: kind.usage(),
         view_formats: &[],
     };
     unsafe { device.create_texture_from_hal::<Vulkan>(wgpu_hal_texture, &texture_desc) }
@@ -0,0 +1,300 @@
+use std::ops::Range;
+
+use bytemuck::{Pod, Zeroable};
+use openxr::sys;
+use wgpu::{
+    Buffer, BufferUsages, Device, PrimitiveTopology, VertexAttribute, VertexBufferLayout,
+    VertexFormat, VertexStepMode,
+    util::{BufferInitDescriptor, DeviceExt},
+};
+
+use crate::{Error, SessionEvent, cvt};
+
+/// Which mask to fetch, see `XrVisibilityMaskTypeKHR`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VisibilityMaskType {
+    /// Triangles covering the pixels that are never visible, for masking them out.
+    HiddenTriangleMesh,
+    /// Triangles covering the pixels that are visible.
+    VisibleTriangleMesh,
+    /// Outline of the visible area, drawn as a line list.
+    LineLoop,
+}
+
+impl VisibilityMaskType {
+    fn xr_type(self) -> sys::VisibilityMaskTypeKHR {
+        match self {
+            VisibilityMaskType::HiddenTriangleMesh => {
+                sys::VisibilityMaskTypeKHR::HIDDEN_TRIANGLE_MESH
+            }
+            VisibilityMaskType::VisibleTriangleMesh => {
+                sys::VisibilityMaskTypeKHR::VISIBLE_TRIANGLE_MESH
+            }
+            VisibilityMaskType::LineLoop => sys::VisibilityMaskTypeKHR::LINE_LOOP,
+        }
+    }
+
+    pub fn topology(self) -> PrimitiveTopology {
+        match self {
+            VisibilityMaskType::HiddenTriangleMesh | VisibilityMaskType::VisibleTriangleMesh => {
+                PrimitiveTopology::TriangleList
+            }
+            VisibilityMaskType::LineLoop => PrimitiveTopology::LineList,
+        }
+    }
+}
+
+/// Vertex of a visibility mask, 16 bytes.
+///
+/// Positions are on the `z = -1` plane in view space, so the clip position is
+/// `projection * vec4(position, -1.0, 1.0)` with the projection matrix of the view.
+///
+/// The meshes of all views share one buffer. In a multiview render pass every view draws every
+/// vertex, so the vertex shader should discard vertices whose `view_index` doesn't match
+/// `@builtin(view_index)`, for example by outputting a degenerate position.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Pod, Zeroable)]
+pub struct VisibilityMaskVertex {
+    pub position: [f32; 2],
+    pub view_index: u32,
+    pub _padding: u32,
+}
+
+impl VisibilityMaskVertex {
+    const ATTRIBUTES: [VertexAttribute; 2] = [
+        VertexAttribute {
+            format: VertexFormat::Float32x2,
+            offset: 0,
+            shader_location: 0,
+        },
+        VertexAttribute {
+            format: VertexFormat::Uint32,
+            offset: 8,
+            shader_location: 1,
+        },
+    ];
+
+    /// Layout of the vertex buffer, with the position at location 0 and the view index at 1.
+    pub fn layout() -> VertexBufferLayout<'static> {
+        VertexBufferLayout {
+            array_stride: size_of::<Self>() as u64,
+            step_mode: VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+/// Visibility mask of every view, in WGPU buffers, using `XR_KHR_visibility_mask`.
+pub struct VisibilityMask {
+    xr_session: openxr::Session<openxr::Vulkan>,
+    fp: openxr::raw::VisibilityMaskKHR,
+    view_type: openxr::ViewConfigurationType,
+    view_count: u32,
+    mask_type: VisibilityMaskType,
+    vertex_buffer: Buffer,
+    index_buffer: Buffer,
+    view_ranges: Vec<Range<u32>>,
+}
+
+impl VisibilityMask {
+    /// Fetches the masks of all views, and uploads them.
+    ///
+    /// Fails if `XR_KHR_visibility_mask` isn't enabled on the instance.
+    pub fn new(
+        device: &Device,
+        xr_session: &openxr::Session<openxr::Vulkan>,
+        view_type: openxr::ViewConfigurationType,
+        view_count: u32,
+        mask_type: VisibilityMaskType,
+    ) -> Result<Self, Error> {
+        let fp = *xr_session
+            .instance()
+            .exts()
+            .khr_visibility_mask
+            .as_ref()
+            .ok_or_else(|| Error::missing_extension("XR_KHR_visibility_mask"))?;
+
+        let (vertex_buffer, index_buffer, view_ranges) =
+            create_buffers(device, xr_session, &fp, view_type, view_count, mask_type)?;
+
+        Ok(Self {
+            xr_session: xr_session.clone(),
+            fp,
+            view_type,
+            view_count,
+            mask_type,
+            vertex_buffer,
+            index_buffer,
+            view_ranges,
+        })
+    }
+
+    pub fn mask_type(&self) -> VisibilityMaskType {
+        self.mask_type
+    }
+
+    pub fn vertex_buffer(&self) -> &Buffer {
+        &self.vertex_buffer
+    }
+
+    /// `u32` indices into the vertex buffer, draw them with `mask_type().topology()`.
+    pub fn index_buffer(&self) -> &Buffer {
+        &self.index_buffer
+    }
+
+    /// Total indices of all views.
+    pub fn index_count(&self) -> u32 {
+        self.view_ranges.last().map_or(0, |range| range.end)
+    }
+
+    /// Range of indices of every view, for drawing views separately without multiview.
+    pub fn view_ranges(&self) -> &[Range<u32>] {
+        &self.view_ranges
+    }
+
+    /// Fetches and uploads the masks again, replacing the buffers.
+    pub fn refresh(&mut self, device: &Device) -> Result<(), Error> {
+        let (vertex_buffer, index_buffer, view_ranges) = create_buffers(
+            device,
+            &self.xr_session,
+            &self.fp,
+            self.view_type,
+            self.view_count,
+            self.mask_type,
+        )?;
+
+        self.vertex_buffer = vertex_buffer;
+        self.index_buffer = index_buffer;
+        self.view_ranges = view_ranges;
+
+        Ok(())
+    }
+
+    /// Handles events from `SessionDriver::poll_events`, refreshing the masks when they change.
+    ///
+    /// Returns if the buffers were replaced, in which case bind groups or cached references to them
+    /// should be updated.
+    pub fn handle_event(&mut self, device: &Device, event: &SessionEvent) -> Result<bool, Error> {
+        let SessionEvent::VisibilityMaskChanged {
+            view_configuration_type,
+            ..
+        } = *event
+        else {
+            return Ok(false);
+        };
+        if view_configuration_type != self.view_type {
+            return Ok(false);
+        }
+
+        self.refresh(device)?;
+
+        Ok(true)
+    }
+}
+
+fn create_buffers(
+    device: &Device,
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    fp: &openxr::raw::VisibilityMaskKHR,
+    view_type: openxr::ViewConfigurationType,
+    view_count: u32,
+    mask_type: VisibilityMaskType,
+) -> Result<(Buffer, Buffer, Vec<Range<u32>>), Error> {
+    let mut vertices = Vec::new();
+    let mut indices = Vec::new();
+    let mut view_ranges = Vec::new();
+
+    for view_index in 0..view_count {
+        let (view_vertices, view_indices) =
+            get_visibility_mask(xr_session, fp, view_type, view_index, mask_type)?;
+
+        let base = vertices.len() as u32;
+        let start = indices.len() as u32;
+
+        vertices.extend(view_vertices.iter().map(|vertex| VisibilityMaskVertex {
+            position: [vertex.x, vertex.y],
+            view_index,
+            _padding: 0,
+        }));
+
+        match mask_type {
+            VisibilityMaskType::HiddenTriangleMesh | VisibilityMaskType::VisibleTriangleMesh => {
+                indices.extend(view_indices.iter().map(|index| base + index));
+            }
+            VisibilityMaskType::LineLoop => {
+                // WGPU has no line loops, so close the loop as a line list
+                let next = view_indices.iter().cycle().skip(1);
+                for (index, next) in view_indices.iter().zip(next) {
+                    indices.extend([base + index, base + next]);
+                }
+            }
+        }
+
+        view_ranges.push(start..indices.len() as u32);
+    }
+
+    let vertex_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("visibility mask vertices"),
+        contents: bytemuck::cast_slice(&vertices),
+        usage: BufferUsages::VERTEX,
+    });
+    let index_buffer = device.create_buffer_init(&BufferInitDescriptor {
+        label: Some("visibility mask indices"),
+        contents: bytemuck::cast_slice(&indices),
+        usage: BufferUsages::INDEX,
+    });
+
+    Ok((vertex_buffer, index_buffer, view_ranges))
+}
+
+fn get_visibility_mask(
+    xr_session: &openxr::Session<openxr::Vulkan>,
+    fp: &openxr::raw::VisibilityMaskKHR,
+    view_type: openxr::ViewConfigurationType,
+    view_index: u32,
+    mask_type: VisibilityMaskType,
+) -> Result<(Vec<openxr::Vector2f>, Vec<u32>), Error> {
+    let mut mask = sys::VisibilityMaskKHR {
+        ty: sys::VisibilityMaskKHR::TYPE,
+        next: std::ptr::null_mut(),
+        vertex_capacity_input: 0,
+        vertex_count_output: 0,
+        vertices: std::ptr::null_mut(),
+        index_capacity_input: 0,
+        index_count_output: 0,
+        indices: std::ptr::null_mut(),
+    };
+
+    // First call gets the sizes, the second fills the buffers
+    unsafe {
+        cvt((fp.get_visibility_mask)(
+            xr_session.as_raw(),
+            view_type,
+            view_index,
+            mask_type.xr_type(),
+            &mut mask,
+        ))?;
+    }
+
+    let mut vertices = vec![openxr::Vector2f::default(); mask.vertex_count_output as usize];
+    let mut indices = vec![0; mask.index_count_output as usize];
+    mask.vertex_capacity_input = vertices.len() as u32;
+    mask.vertices = vertices.as_mut_ptr();
+    mask.index_capacity_input = indices.len() as u32;
+    mask.indices = indices.as_mut_ptr();
+
+    unsafe {
+        cvt((fp.get_visibility_mask)(
+            xr_session.as_raw(),
+            view_type,
+            view_index,
+            mask_type.xr_type(),
+            &mut mask,
+        ))?;
+    }
+
+    vertices.truncate(mask.vertex_count_output as usize);
+    indices.truncate(mask.index_count_output as usize);
+
+    Ok((vertices, indices))
+}
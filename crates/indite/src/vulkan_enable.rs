@@ -0,0 +1,226 @@
+use std::ffi::{CStr, CString, c_char};
+
+use ash::vk::{self, Handle};
+use openxr::sys;
+
+use crate::{Error, cvt};
+
+/// OpenXR extension the runtime provides Vulkan through.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VulkanEnable {
+    /// `XR_KHR_vulkan_enable2`, the runtime creates the Vulkan instance and device.
+    Enable2,
+    /// `XR_KHR_vulkan_enable`, indite creates the Vulkan instance and device, enabling the
+    /// extensions the runtime lists.
+    Enable,
+}
+
+impl VulkanEnable {
+    /// Picks the extension enabled on the instance, preferring `XR_KHR_vulkan_enable2`.
+    pub fn detect(xr_instance: &openxr::Instance) -> Result<Self, Error> {
+        let exts = xr_instance.exts();
+        if exts.khr_vulkan_enable2.is_some() {
+            Ok(VulkanEnable::Enable2)
+        } else if exts.khr_vulkan_enable.is_some() {
+            Ok(VulkanEnable::Enable)
+        } else {
+            Err(Error::VulkanNotEnabled)
+        }
+    }
+
+    pub(crate) fn graphics_requirements(
+        self,
+        xr_instance: &openxr::Instance,
+        xr_system: openxr::SystemId,
+    ) -> Result<openxr::vulkan::Requirements, Error> {
+        if self == VulkanEnable::Enable2 {
+            let reqs = xr_instance.graphics_requirements::<openxr::Vulkan>(xr_system)?;
+            return Ok(reqs);
+        }
+
+        // openxr only wraps the XR_KHR_vulkan_enable2 version of this
+        let mut reqs = sys::GraphicsRequirementsVulkanKHR {
+            ty: sys::GraphicsRequirementsVulkanKHR::TYPE,
+            next: std::ptr::null_mut(),
+            min_api_version_supported: openxr::Version::new(0, 0, 0),
+            max_api_version_supported: openxr::Version::new(0, 0, 0),
+        };
+        unsafe {
+            cvt((legacy_fp(xr_instance)?.get_vulkan_graphics_requirements)(
+                xr_instance.as_raw(),
+                xr_system,
+                &mut reqs,
+            ))?;
+        }
+
+        Ok(openxr::vulkan::Requirements {
+            min_api_version_supported: reqs.min_api_version_supported,
+            max_api_version_supported: reqs.max_api_version_supported,
+        })
+    }
+
+    pub(crate) fn physical_device(
+        self,
+        xr_instance: &openxr::Instance,
+        xr_system: openxr::SystemId,
+        vk_instance: vk::Instance,
+    ) -> Result<vk::PhysicalDevice, Error> {
+        let vk_physical_device_raw = match self {
+            VulkanEnable::Enable2 => unsafe {
+                xr_instance
+                    .vulkan_graphics_device(xr_system, vk_instance.as_raw() as _)
+                    .map_err(Error::NoPhysicalDevice)?
+            },
+            VulkanEnable::Enable => {
+                let mut vk_physical_device_raw = std::ptr::null();
+                unsafe {
+                    cvt((legacy_fp(xr_instance)?.get_vulkan_graphics_device)(
+                        xr_instance.as_raw(),
+                        xr_system,
+                        vk_instance.as_raw() as _,
+                        &mut vk_physical_device_raw,
+                    ))
+                    .map_err(Error::NoPhysicalDevice)?;
+                }
+                vk_physical_device_raw
+            }
+        };
+
+        Ok(vk::PhysicalDevice::from_raw(vk_physical_device_raw as _))
+    }
+}
+
+/// Instance extensions the runtime needs enabled, for `XR_KHR_vulkan_enable`.
+pub(crate) fn legacy_instance_extensions(
+    xr_instance: &openxr::Instance,
+    xr_system: openxr::SystemId,
+) -> Result<Vec<CString>, Error> {
+    let fp = legacy_fp(xr_instance)?;
+    get_extension_list(|capacity, count, buffer| unsafe {
+        (fp.get_vulkan_instance_extensions)(
+            xr_instance.as_raw(),
+            xr_system,
+            capacity,
+            count,
+            buffer,
+        )
+    })
+}
+
+/// Device extensions the runtime needs enabled, for `XR_KHR_vulkan_enable`.
+pub(crate) fn legacy_device_extensions(
+    xr_instance: &openxr::Instance,
+    xr_system: openxr::SystemId,
+) -> Result<Vec<CString>, Error> {
+    let fp = legacy_fp(xr_instance)?;
+    get_extension_list(|capacity, count, buffer| unsafe {
+        (fp.get_vulkan_device_extensions)(xr_instance.as_raw(), xr_system, capacity, count, buffer)
+    })
+}
+
+/// Pointers to `extensions`, followed by the ones in `runtime_extensions` not already in it.
+pub(crate) fn merge_extensions(
+    extensions: &[&'static CStr],
+    runtime_extensions: &[CString],
+) -> Vec<*const c_char> {
+    let mut merged: Vec<_> = extensions.iter().map(|name| name.as_ptr()).collect();
+    for name in runtime_extensions {
+        if !extensions.contains(&name.as_c_str()) {
+            merged.push(name.as_ptr());
+        }
+    }
+
+    merged
+}
+
+fn legacy_fp(xr_instance: &openxr::Instance) -> Result<openxr::raw::VulkanEnableKHR, Error> {
+    xr_instance
+        .exts()
+        .khr_vulkan_enable
+        .ok_or_else(|| Error::missing_extension("XR_KHR_vulkan_enable"))
+}
+
+/// Gets a space separated list of extensions with the two call idiom, and splits it.
+fn get_extension_list(
+    mut get: impl FnMut(u32, &mut u32, *mut c_char) -> sys::Result,
+) -> Result<Vec<CString>, Error> {
+    let mut count = 0;
+    cvt(get(0, &mut count, std::ptr::null_mut()))?;
+
+    let mut buffer = vec![0u8; count as usize];
+    cvt(get(
+        buffer.len() as u32,
+        &mut count,
+        buffer.as_mut_ptr() as *mut c_char,
+    ))?;
+    buffer.truncate(count as usize);
+
+    // The count includes the null terminator
+    let list = CStr::from_bytes_until_nul(&buffer)
+        .map(CStr::to_bytes)
+        .unwrap_or(&buffer);
+    let extensions = list
+        .split(|&byte| byte == b' ')
+        .filter(|name| !name.is_empty())
+        .map(|name| CString::new(name).expect("list was cut off at the first nul"))
+        .collect();
+
+    Ok(extensions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers the two call idiom like a runtime listing `list`.
+    fn runtime_list(list: &'static [u8]) -> impl FnMut(u32, &mut u32, *mut c_char) -> sys::Result {
+        move |capacity, count, buffer| {
+            *count = list.len() as u32;
+            if capacity == 0 {
+                return sys::Result::SUCCESS;
+            }
+            if (capacity as usize) < list.len() {
+                return sys::Result::ERROR_SIZE_INSUFFICIENT;
+            }
+
+            unsafe { std::ptr::copy_nonoverlapping(list.as_ptr(), buffer as *mut u8, list.len()) };
+            sys::Result::SUCCESS
+        }
+    }
+
+    #[test]
+    fn splits_extension_list() {
+        let extensions =
+            get_extension_list(runtime_list(b"VK_KHR_a  VK_KHR_b VK_EXT_c\0")).unwrap();
+
+        assert_eq!(extensions, [c"VK_KHR_a", c"VK_KHR_b", c"VK_EXT_c"]);
+    }
+
+    #[test]
+    fn handles_empty_extension_list() {
+        assert!(get_extension_list(runtime_list(b"\0")).unwrap().is_empty());
+        assert!(get_extension_list(runtime_list(b"")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_runtime_errors() {
+        let result = get_extension_list(|_, _, _| sys::Result::ERROR_RUNTIME_FAILURE);
+
+        assert!(matches!(
+            result,
+            Err(Error::Xr(sys::Result::ERROR_RUNTIME_FAILURE))
+        ));
+    }
+
+    #[test]
+    fn merges_without_duplicates() {
+        let runtime_extensions = [c"VK_KHR_b".to_owned(), c"VK_EXT_c".to_owned()];
+        let merged = merge_extensions(&[c"VK_KHR_a", c"VK_KHR_b"], &runtime_extensions);
+
+        let merged: Vec<_> = merged
+            .into_iter()
+            .map(|name| unsafe { CStr::from_ptr(name) })
+            .collect();
+        assert_eq!(merged, [c"VK_KHR_a", c"VK_KHR_b", c"VK_EXT_c"]);
+    }
+}